@@ -0,0 +1,244 @@
+//! AI token usage tracking, cost estimation and spending cap
+//!
+//! The tracker is shared by every clone of an `AiConfig`, so the table
+//! selection, title cleaning and matching stages all draw from one budget.
+
+use crate::models::{AiPricing, AiUsage, BudgetAction};
+use std::sync::{Arc, Mutex};
+
+// 粗略估算用的提示词规模（token），根据实际提示词长度测得
+const TABLE_SELECTION_BASE_INPUT: usize = 120;
+const TABLE_SELECTION_PER_TABLE_INPUT: usize = 25;
+const TABLE_SELECTION_OUTPUT: usize = 15;
+const CLEANING_BASE_INPUT: usize = 650;
+const CLEANING_PER_WORK_INPUT: usize = 35;
+const CLEANING_PER_WORK_OUTPUT: usize = 90;
+const MATCHING_BASE_INPUT: usize = 450;
+const MATCHING_PER_WORK_INPUT: usize = 90;
+const MATCHING_PER_CANDIDATE_INPUT: usize = 55;
+const MATCHING_PER_WORK_OUTPUT: usize = 70;
+
+/// 运行前估算候选作品数时假定的每部作品候选数
+pub const ASSUMED_CANDIDATES_PER_WORK: usize = 10;
+
+/// token用量估算
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenEstimate {
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+}
+
+impl TokenEstimate {
+    pub fn total(&self) -> usize {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+impl std::ops::Add for TokenEstimate {
+    type Output = TokenEstimate;
+
+    fn add(self, other: TokenEstimate) -> TokenEstimate {
+        TokenEstimate {
+            input_tokens: self.input_tokens + other.input_tokens,
+            output_tokens: self.output_tokens + other.output_tokens,
+        }
+    }
+}
+
+/// 估算表格选择请求的用量
+pub fn estimate_table_selection_usage(table_count: usize) -> TokenEstimate {
+    TokenEstimate {
+        input_tokens: TABLE_SELECTION_BASE_INPUT + table_count * TABLE_SELECTION_PER_TABLE_INPUT,
+        output_tokens: TABLE_SELECTION_OUTPUT,
+    }
+}
+
+/// 估算标题清理和关键字生成的用量
+pub fn estimate_cleaning_usage(work_count: usize, batch_size: usize) -> TokenEstimate {
+    let batches = work_count.div_ceil(batch_size.max(1));
+    TokenEstimate {
+        input_tokens: batches * CLEANING_BASE_INPUT + work_count * CLEANING_PER_WORK_INPUT,
+        output_tokens: work_count * CLEANING_PER_WORK_OUTPUT,
+    }
+}
+
+/// 估算作品匹配的用量
+pub fn estimate_matching_usage(
+    work_count: usize,
+    candidate_count: usize,
+    batch_size: usize,
+) -> TokenEstimate {
+    let batches = work_count.div_ceil(batch_size.max(1));
+    TokenEstimate {
+        input_tokens: batches * MATCHING_BASE_INPUT
+            + work_count * MATCHING_PER_WORK_INPUT
+            + candidate_count * MATCHING_PER_CANDIDATE_INPUT,
+        output_tokens: work_count * MATCHING_PER_WORK_OUTPUT,
+    }
+}
+
+/// 某一时刻的AI用量
+#[derive(Debug, Clone, Default)]
+pub struct UsageSnapshot {
+    pub requests: usize,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    pub estimated: TokenEstimate,
+    pub budget_exceeded: bool,
+}
+
+/// AI预算：记录实际用量和估算用量，并在请求前检查花费上限
+#[derive(Debug, Clone)]
+pub struct AiBudget {
+    pub pricing: AiPricing,
+    pub limit: Option<f64>,
+    pub action: BudgetAction,
+    usage: Arc<Mutex<UsageSnapshot>>,
+}
+
+impl AiBudget {
+    pub fn new(pricing: AiPricing, limit: Option<f64>, action: BudgetAction) -> Self {
+        Self {
+            pricing,
+            limit,
+            action,
+            usage: Arc::new(Mutex::new(UsageSnapshot::default())),
+        }
+    }
+
+    /// 记录一次AI请求及其token用量
+    pub fn record(&self, usage: Option<&AiUsage>) {
+        let mut state = self.usage.lock().unwrap();
+        state.requests += 1;
+        if let Some(usage) = usage {
+            state.input_tokens += usage.prompt_tokens;
+            state.output_tokens += usage.completion_tokens;
+        }
+    }
+
+    /// 累加运行前的用量估算
+    pub fn add_estimate(&self, estimate: TokenEstimate) {
+        let mut state = self.usage.lock().unwrap();
+        state.estimated = state.estimated + estimate;
+    }
+
+    pub fn snapshot(&self) -> UsageSnapshot {
+        self.usage.lock().unwrap().clone()
+    }
+
+    /// 已实际花费的金额
+    pub fn spent(&self) -> f64 {
+        let state = self.usage.lock().unwrap();
+        self.pricing.cost(state.input_tokens, state.output_tokens)
+    }
+
+    /// 估算的总花费
+    pub fn estimated_cost(&self) -> f64 {
+        self.cost_of(&self.snapshot().estimated)
+    }
+
+    pub fn cost_of(&self, estimate: &TokenEstimate) -> f64 {
+        self.pricing.cost(estimate.input_tokens, estimate.output_tokens)
+    }
+
+    /// 超出预算且配置为停止运行
+    pub fn should_stop(&self) -> bool {
+        self.action == BudgetAction::Stop && self.snapshot().budget_exceeded
+    }
+
+    /// 在发送请求前检查预算
+    ///
+    /// 预算充足时返回 `Ok(true)`；超出预算时，配置为降级返回 `Ok(false)`，配置为停止返回错误。
+    pub fn allow(&self, next: &TokenEstimate) -> Result<bool, Box<dyn std::error::Error>> {
+        let Some(limit) = self.limit else {
+            return Ok(true);
+        };
+
+        let projected = self.spent() + self.cost_of(next);
+        if projected <= limit {
+            return Ok(true);
+        }
+
+        let first_time = {
+            let mut state = self.usage.lock().unwrap();
+            !std::mem::replace(&mut state.budget_exceeded, true)
+        };
+
+        match self.action {
+            BudgetAction::Stop => Err(format!(
+                "AI预算已用尽: 预计花费 {:.4} {} 超出上限 {:.4} {}",
+                projected, self.pricing.currency, limit, self.pricing.currency
+            )
+            .into()),
+            BudgetAction::Fallback => {
+                if first_time {
+                    log::warn!(
+                        "AI预算已用尽 (上限 {:.4} {})，后续处理改用非AI方式",
+                        limit, self.pricing.currency
+                    );
+                }
+                Ok(false)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::AiConfig;
+
+    #[test]
+    fn test_ai_budget_fallback_and_stop() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::ai::budget::{estimate_cleaning_usage, estimate_matching_usage};
+        use crate::models::{AiPricing, AiSettings, AiUsage, BudgetAction};
+
+        // 预估用量应随作品数和候选数增长
+        let small = estimate_matching_usage(10, 50, 10);
+        let large = estimate_matching_usage(10, 100, 10);
+        assert!(large.input_tokens > small.input_tokens);
+        assert_eq!(estimate_cleaning_usage(0, 20).total(), 0);
+
+        // 默认不限制预算
+        let unlimited = AiConfig::deepseek();
+        assert!(unlimited.budget.allow(&large)?);
+
+        // 降级模式：超出预算后返回false并记录
+        // 每百万token 1元，预算 0.01 即 10000 token
+        let settings = AiSettings {
+            budget: Some(0.01),
+            on_budget_exceeded: BudgetAction::Fallback,
+            pricing: Some(AiPricing {
+                input_per_million: 1.0,
+                output_per_million: 1.0,
+                currency: "CNY".to_string(),
+            }),
+            ..AiSettings::default()
+        };
+        let ai_config = AiConfig::deepseek().with_settings(&settings);
+        let cloned = ai_config.clone();
+        cloned.budget.record(Some(&AiUsage {
+            prompt_tokens: 4_000,
+            completion_tokens: 1_000,
+        }));
+        // 克隆的配置共享同一个用量跟踪器
+        assert_eq!(ai_config.budget.snapshot().input_tokens, 4_000);
+        assert!(ai_config.budget.spent() > 0.0);
+        assert!(!ai_config.budget.allow(&large)?);
+        assert!(ai_config.budget.snapshot().budget_exceeded);
+
+        // 停止模式：超出预算后返回错误
+        let settings = AiSettings {
+            on_budget_exceeded: BudgetAction::Stop,
+            ..settings
+        };
+        let ai_config = AiConfig::deepseek().with_settings(&settings);
+        assert!(ai_config.budget.allow(&small).is_ok());
+        ai_config.budget.record(Some(&AiUsage {
+            prompt_tokens: 10_000,
+            completion_tokens: 0,
+        }));
+        assert!(ai_config.budget.allow(&small).is_err());
+
+        Ok(())
+    }
+}
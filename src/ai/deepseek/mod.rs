@@ -1,8 +1,18 @@
+use crate::ai::budget::{
+    ASSUMED_CANDIDATES_PER_WORK, estimate_cleaning_usage, estimate_matching_usage,
+    estimate_table_selection_usage,
+};
+use crate::ai::prompts;
 use crate::models::{
-    AiConfig, AiMessage, AiProvider, AiRequest, AiResponse, AnimeWork, Statistics, TableInfo,
+    AiConfig, AiMessage, AiRequest, AiResponse, AnimeWork, CleaningMethod, Statistics, TableInfo,
 };
+use crate::text::cleaner::pre_clean_title;
+use crate::text::keywords::{expand_keywords, rule_based_work};
+use crate::text::normalize::normalize_keywords;
+use futures::stream::{self, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
-use std::env;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub async fn match_and_process_with_ai<'a>(
     description: &'a str,
@@ -16,26 +26,17 @@ pub async fn match_and_process_with_ai<'a>(
     ),
     Box<dyn std::error::Error>,
 > {
-    let api_key = match ai_config.provider {
-        AiProvider::DeepSeek => env::var("DEEPSEEK_API_KEY")?,
-        // 未来支持其他AI提供商
-        // AiProvider::OpenAi => env::var("OPENAI_API_KEY")?,
-        // AiProvider::Claude => env::var("CLAUDE_API_KEY")?,
-    };
+    let api_key = ai_config.api_key()?;
 
     let mut stats = Statistics {
-        total_works_from_table: 0,
-        works_with_undetermined_date: 0,
-        works_processed_by_ai: 0,
-        works_with_bangumi_info: 0,
-        works_without_bangumi_info: 0,
-        qb_rules_generated: 0,
-        qb_rules_failed: 0,
-        ai_requests_count: 0,
-        ai_input_tokens: 0,
-        ai_output_tokens: 0,
+        ai_cost_currency: ai_config.budget.pricing.currency.clone(),
+        ai_budget: ai_config.budget.limit,
+        ..Default::default()
     };
 
+    let table_selection_estimate = estimate_table_selection_usage(tables.len());
+    ai_config.budget.add_estimate(table_selection_estimate);
+
     // 准备表格信息 - 只发送表格标题作为锚点
     let table_descriptions: Vec<String> = tables
         .iter()
@@ -43,18 +44,15 @@ pub async fn match_and_process_with_ai<'a>(
         .collect();
 
     // 步骤1: 先让AI选择表格
-    let table_selection_prompt = format!(
-        "用户提供的描述是: '{}'\n\n
-        以下是网页中找到的表格标题:\n{}
-        \n\n请根据用户描述，判断哪个表格标题最相关。请返回表格的序号（从0开始）。\n\n
-        请返回JSON格式：{{\"table_index\": 数字}}",
-        description,
-        table_descriptions
-            .iter()
-            .enumerate()
-            .map(|(i, desc)| format!("[{}] {}", i, desc))
-            .collect::<Vec<_>>()
-            .join("\n\n")
+    let tables_text = table_descriptions
+        .iter()
+        .enumerate()
+        .map(|(i, desc)| format!("[{}] {}", i, desc))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let table_selection_prompt = ai_config.prompts.render(
+        prompts::TABLE_SELECTION,
+        &[("description", description), ("tables", &tables_text)],
     );
 
     let table_selection_request = AiRequest {
@@ -68,40 +66,39 @@ pub async fn match_and_process_with_ai<'a>(
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(180))
         .build()?;
-    let response = client
-        .post(&ai_config.api_url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&table_selection_request)
-        .send()
-        .await?;
 
-    let api_response: AiResponse = response.json().await?;
+    let mut selected_table_index = 0;
+    if ai_config.budget.allow(&table_selection_estimate)? {
+        let response = client
+            .post(&ai_config.api_url)
+            .header("Authorization", format!("Bearer {}", api_key))
+            .header("Content-Type", "application/json")
+            .json(&table_selection_request)
+            .send()
+            .await?;
 
-    // 记录第一次AI请求的token使用情况
-    stats.ai_requests_count += 1;
-    if let Some(usage) = &api_response.usage {
-        stats.ai_input_tokens += usage.prompt_tokens;
-        stats.ai_output_tokens += usage.completion_tokens;
-    }
+        let api_response: AiResponse = response.json().await?;
 
-    let mut selected_table_index = 0;
-    if let Some(choice) = api_response.choices.first() {
-        let content = choice.message.content.trim();
+        // 记录第一次AI请求的token使用情况
+        ai_config.budget.record(api_response.usage.as_ref());
 
-        // 提取JSON内容，处理markdown代码块
-        let json_content = if content.starts_with("```json") && content.ends_with("```") {
-            content[7..content.len() - 3].trim()
-        } else if content.starts_with("```") && content.ends_with("```") {
-            content[3..content.len() - 3].trim()
-        } else {
-            content
-        };
+        if let Some(choice) = api_response.choices.first() {
+            let content = choice.message.content.trim();
 
-        if let Ok(processed_data) = serde_json::from_str::<serde_json::Value>(json_content)
-            && let Some(table_index) = processed_data["table_index"].as_u64()
-        {
-            selected_table_index = table_index as usize;
+            // 提取JSON内容，处理markdown代码块
+            let json_content = if content.starts_with("```json") && content.ends_with("```") {
+                content[7..content.len() - 3].trim()
+            } else if content.starts_with("```") && content.ends_with("```") {
+                content[3..content.len() - 3].trim()
+            } else {
+                content
+            };
+
+            if let Ok(processed_data) = serde_json::from_str::<serde_json::Value>(json_content)
+                && let Some(table_index) = processed_data["table_index"].as_u64()
+            {
+                selected_table_index = table_index as usize;
+            }
         }
     }
 
@@ -121,9 +118,37 @@ pub async fn match_and_process_with_ai<'a>(
         log::info!("过滤掉 {} 个日期未定的作品", undetermined_date_count);
     }
 
-    // 步骤3: 将实际作品分批发送给AI进行清理和关键字生成
+    // 步骤3: 全部作品分批发送给AI生成译名关键字，规则能确定的标题不再交给AI清理
     let batch_size = 20; // 每批处理20个作品
-    let mut processed_works = Vec::new();
+    let rule_cleaned_count = raw_works
+        .iter()
+        .filter(|work| pre_clean_title(&work.original_title).confident)
+        .count();
+    log::info!(
+        "规则清理 {} 个作品的标题，{} 个作品的标题交给AI清理，全部作品由AI生成译名",
+        rule_cleaned_count,
+        raw_works.len() - rule_cleaned_count
+    );
+
+    // 运行前估算：清理阶段按作品数估算，匹配阶段按假定的候选作品数估算
+    let cleaning_estimate = estimate_cleaning_usage(raw_works.len(), batch_size);
+    ai_config.budget.add_estimate(cleaning_estimate);
+    let run_estimate = table_selection_estimate
+        + cleaning_estimate
+        + estimate_matching_usage(
+            raw_works.len(),
+            raw_works.len() * ASSUMED_CANDIDATES_PER_WORK,
+            10,
+        );
+    log::info!(
+        "预计本次运行AI用量: {} tokens，约 {:.4} {}",
+        run_estimate.total(),
+        ai_config.budget.cost_of(&run_estimate),
+        ai_config.budget.pricing.currency
+    );
+    if let Some(limit) = ai_config.budget.limit {
+        log::info!("AI预算上限: {:.4} {}", limit, ai_config.budget.pricing.currency);
+    }
 
     // 创建进度条 - 在整个AI处理过程中共享
     let total_works = raw_works.len();
@@ -138,120 +163,72 @@ pub async fn match_and_process_with_ai<'a>(
     pb.enable_steady_tick(std::time::Duration::from_millis(250));
     pb.set_message("AI处理中...");
 
-    for (batch_index, batch) in raw_works.chunks(batch_size).enumerate() {
-        let current_batch_start = batch_index * batch_size;
-        let current_batch_end = (batch_index + 1) * batch_size;
-
-        pb.set_message(format!(
-            "处理第 {} 批作品 ({}-{}/{})",
-            batch_index + 1,
-            current_batch_start + 1,
-            current_batch_end.min(total_works),
-            total_works
-        ));
-
-        let works_for_processing: Vec<String> = batch
-            .iter()
-            .map(|work| {
-                format!(
-                    "原标题: {}, 放送日期: {:?}",
-                    work.original_title, work.air_date
-                )
-            })
-            .collect();
-
-        let works_processing_prompt = format!(
-            "以下是需要处理的动画作品列表：\n\n{}
-            \n\n请为每个作品执行以下操作：\n1. 清理标题，去除无用信息如【日本語吹替版】等，但保留季号信息和副标题\n   - 重要：副标题如『』、【】、（）、《》、「」中的内容都是重要信息，必须保留\n   - 例如：'青のミブロ 第二期「芹沢暗殺編」' 中的 '「芹沢暗殺編」' 必须保留\n2. 生成5-8个搜索关键字 - 请包含：\n   - 日文原标题（包含中点・和空格变体）\n   - 常见中文译名\n   - 英文名称\n   - 其他常见搜索变体\n   特别提醒：\n   - 主标题和副标题同等重要，至少有一个关键字必须同时包含主副标题（用半角空格分割）\n   - 对于包含中点的日文标题，请同时生成去掉中点用空格替代的版本\n   - 对于可能使用特殊符号（如♥、☆等）的经典作品，请生成包含这些符号变体的关键字\n   - 对于经典作品的重制/新作，请包含原版作品的各种常见名称变体\n   - 对于经典作品如'キャッツ・アイ'，请包含'キャッツ アイ'（无中点）和'猫眼三姐妹'等常见中文译名\n   - 生成的关键字中尽量不要带特殊符号（如♥、☆等），以免干扰搜索结果\n   - 如果标题中有起到分隔作用的特殊符号（如日文中点・、爱心♥、星星☆等），在生成关键字时应替换成半角空格\n\n
-            请返回JSON格式：{{\"works\": [{{\"original_title\": \"原标题\", \"cleaned_title\": \"清理后标题\", \"keywords\": [\"关键词1\", \"关键词2\"]}}, ...]}}",
-            works_for_processing.join("\n")
-        );
-
-        let works_processing_request = AiRequest {
-            model: ai_config.model.clone(),
-            messages: vec![AiMessage {
-                role: "user".to_string(),
-                content: works_processing_prompt,
-            }],
-        };
-
-        let response = client
-            .post(&ai_config.api_url)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("Content-Type", "application/json")
-            .json(&works_processing_request)
-            .send()
-            .await?;
-
-        let api_response: AiResponse = response.json().await?;
-
-        // 记录AI请求的token使用情况
-        stats.ai_requests_count += 1;
-        if let Some(usage) = &api_response.usage {
-            stats.ai_input_tokens += usage.prompt_tokens;
-            stats.ai_output_tokens += usage.completion_tokens;
-        }
-
-        if let Some(choice) = api_response.choices.first() {
-            let content = choice.message.content.trim();
-
-            // 提取JSON内容，处理markdown代码块
-            let json_content = if content.starts_with("```json") && content.ends_with("```") {
-                content[7..content.len() - 3].trim()
-            } else if content.starts_with("```") && content.ends_with("```") {
-                content[3..content.len() - 3].trim()
-            } else {
-                content
-            };
-
-            // 尝试从JSON中提取处理后的信息
-            if let Ok(processed_data) = serde_json::from_str::<serde_json::Value>(json_content)
-                && let Some(works_array) = processed_data["works"].as_array()
-            {
-                for (i, work_data) in works_array.iter().enumerate() {
-                    let batch_offset = batch_index * batch_size + i;
-                    if batch_offset < raw_works.len()
-                        && let (Some(original_title), Some(cleaned_title), Some(keywords_array)) = (
-                            work_data["original_title"].as_str(),
-                            work_data["cleaned_title"].as_str(),
-                            work_data["keywords"].as_array(),
-                        )
-                    {
-                        let keywords: Vec<String> = keywords_array
-                            .iter()
-                            .filter_map(|k| k.as_str().map(|s| s.to_string()))
-                            .collect();
-
-                        // 保持原有的air_date
-                        let original_work = &raw_works[batch_offset];
-                        processed_works.push(AnimeWork {
-                            original_title: original_title.to_string(),
-                            cleaned_title: cleaned_title.to_string(),
-                            air_date: original_work.air_date,
-                            keywords,
-                        });
+    // 各批次并发处理，结果按原顺序合并
+    let total_batches = total_works.div_ceil(batch_size);
+    let completed_batches = AtomicUsize::new(0);
+    let batch_results: Vec<_> = stream::iter(raw_works.chunks(batch_size))
+        .map(|batch| {
+            let (client, api_key, pb) = (&client, &api_key, &pb);
+            let completed_batches = &completed_batches;
+            async move {
+                ai_config.rate_limiter.acquire().await;
+                let result = process_works_batch(client, api_key, ai_config, batch, batch_size).await;
+
+                // 更新进度条
+                let completed = completed_batches.fetch_add(1, Ordering::SeqCst) + 1;
+                pb.inc(batch.len() as u64);
+                pb.set_message(format!(
+                    "已完成第 {}/{} 批作品 (共{}个)",
+                    completed, total_batches, total_works
+                ));
+
+                result
+            }
+        })
+        .buffered(ai_config.concurrency.max(1))
+        .collect()
+        .await;
+
+    // 按作品在表格中的位置记录结果，失败的批次只记录警告，其中的作品改用规则处理
+    let mut ai_processed: HashMap<usize, AnimeWork> = HashMap::new();
+    for (batch_index, batch_result) in batch_results.into_iter().enumerate() {
+        match batch_result {
+            Ok(works) => {
+                for (offset, work) in works.into_iter().enumerate() {
+                    if let Some(work) = work {
+                        ai_processed.insert(batch_index * batch_size + offset, work);
                     }
                 }
             }
+            // 预算用尽且配置为停止时仍然中止运行
+            Err(e) if ai_config.budget.should_stop() => return Err(e),
+            Err(e) => log::warn!("第 {}/{} 批作品AI处理失败，改用规则处理: {}", batch_index + 1, total_batches, e),
         }
-
-        // 更新进度条
-        pb.inc(batch.len() as u64);
-
-        // 短暂延迟避免API限制
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
     }
 
     // 完成进度条
     pb.finish_with_message("AI处理完成");
 
-    // 如果AI处理失败，使用原始作品
-    if processed_works.is_empty() {
-        log::warn!("AI作品处理失败，使用原始作品");
-        processed_works = raw_works.clone();
+    // 按原顺序合并，AI未返回的作品改用规则处理
+    if !raw_works.is_empty() && ai_processed.is_empty() {
+        log::warn!("AI作品处理失败，改用规则清理");
     }
+    let processed_works: Vec<AnimeWork> = raw_works
+        .iter()
+        .enumerate()
+        .map(|(index, work)| {
+            ai_processed
+                .remove(&index)
+                .unwrap_or_else(|| rule_based_work(work))
+        })
+        .collect();
 
-    stats.works_processed_by_ai = processed_works.len();
+    stats.works_processed_by_ai = processed_works
+        .iter()
+        .filter(|work| work.cleaned_by == CleaningMethod::Ai)
+        .count();
+    stats.works_cleaned_by_rule = processed_works.len() - stats.works_processed_by_ai;
+    stats.apply_ai_usage(&ai_config.budget);
     let processed_works_clone = processed_works.clone();
     Ok((
         Some((matched_table, processed_works)),
@@ -259,3 +236,118 @@ pub async fn match_and_process_with_ai<'a>(
         stats,
     ))
 }
+
+/// 将一批作品发送给AI进行标题清理和关键字生成
+///
+/// 规则能确定的标题直接附上清理结果，AI只需生成关键字；结果按位置对应到输入的作品，
+/// AI没有返回有效结果的位置为None。
+async fn process_works_batch(
+    client: &reqwest::Client,
+    api_key: &str,
+    ai_config: &AiConfig,
+    batch: &[AnimeWork],
+    batch_size: usize,
+) -> Result<Vec<Option<AnimeWork>>, Box<dyn std::error::Error>> {
+    let pre_cleaned: Vec<_> = batch.iter().map(|work| pre_clean_title(&work.original_title)).collect();
+    let works_for_processing: Vec<String> = batch
+        .iter()
+        .zip(&pre_cleaned)
+        .map(|(work, pre_cleaned)| {
+            if pre_cleaned.confident {
+                format!(
+                    "原标题: {}, 已清理标题: {}, 放送日期: {:?}",
+                    work.original_title, pre_cleaned.title, work.air_date
+                )
+            } else {
+                format!(
+                    "原标题: {}, 放送日期: {:?}",
+                    work.original_title, work.air_date
+                )
+            }
+        })
+        .collect();
+
+    let works_processing_prompt = ai_config.prompts.render(
+        prompts::TITLE_CLEANING,
+        &[("works", &works_for_processing.join("\n"))],
+    );
+
+    // 预算不足时跳过AI，改用规则清理标题和生成关键字
+    if !ai_config.budget.allow(&estimate_cleaning_usage(batch.len(), batch_size))? {
+        return Ok(batch.iter().map(|work| Some(rule_based_work(work))).collect());
+    }
+
+    let works_processing_request = AiRequest {
+        model: ai_config.model.clone(),
+        messages: vec![AiMessage {
+            role: "user".to_string(),
+            content: works_processing_prompt,
+        }],
+    };
+
+    let response = client
+        .post(&ai_config.api_url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .header("Content-Type", "application/json")
+        .json(&works_processing_request)
+        .send()
+        .await?;
+
+    let api_response: AiResponse = response.json().await?;
+
+    // 记录AI请求的token使用情况
+    ai_config.budget.record(api_response.usage.as_ref());
+
+    let mut processed_works = vec![None; batch.len()];
+    if let Some(choice) = api_response.choices.first() {
+        let content = choice.message.content.trim();
+
+        // 提取JSON内容，处理markdown代码块
+        let json_content = if content.starts_with("```json") && content.ends_with("```") {
+            content[7..content.len() - 3].trim()
+        } else if content.starts_with("```") && content.ends_with("```") {
+            content[3..content.len() - 3].trim()
+        } else {
+            content
+        };
+
+        // 尝试从JSON中提取处理后的信息
+        if let Ok(processed_data) = serde_json::from_str::<serde_json::Value>(json_content)
+            && let Some(works_array) = processed_data["works"].as_array()
+        {
+            for (((processed, original_work), pre_cleaned), work_data) in
+                processed_works.iter_mut().zip(batch).zip(&pre_cleaned).zip(works_array)
+            {
+                // 规则能确定的标题不采用AI的清理结果
+                let (cleaned_title, cleaned_by) = if pre_cleaned.confident {
+                    (Some(pre_cleaned.title.as_str()), CleaningMethod::Rule)
+                } else {
+                    (work_data["cleaned_title"].as_str(), CleaningMethod::Ai)
+                };
+                if let (Some(cleaned_title), Some(keywords_array)) = (cleaned_title, work_data["keywords"].as_array()) {
+                    let keywords: Vec<String> = keywords_array
+                        .iter()
+                        .filter_map(|k| k.as_str().map(|s| s.to_string()))
+                        .collect();
+                    // 符号、季号和副标题的变体由规则生成，与AI提供的译名合并
+                    let keywords: Vec<String> = keywords
+                        .into_iter()
+                        .chain(expand_keywords(&original_work.original_title))
+                        .collect();
+                    let keywords = normalize_keywords(&keywords);
+
+                    // 保持原有的原标题和air_date，不使用AI回显的标题
+                    *processed = Some(AnimeWork {
+                        original_title: original_work.original_title.clone(),
+                        cleaned_title: cleaned_title.to_string(),
+                        air_date: original_work.air_date,
+                        keywords,
+                        cleaned_by,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(processed_works)
+}
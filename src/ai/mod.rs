@@ -1,2 +1,4 @@
+pub mod budget;
 pub mod deepseek;
-pub mod object_matcher;
\ No newline at end of file
+pub mod object_matcher;
+pub mod prompts;
\ No newline at end of file
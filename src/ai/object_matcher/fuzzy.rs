@@ -0,0 +1,293 @@
+//! Deterministic local matcher
+//!
+//! Scores candidates by title/alias similarity (character bigrams and edit
+//! distance), air-date proximity and Bangumi rank, so works can be matched
+//! without an API key, candidate lists can be pruned before they reach the
+//! prompt, and obvious matches can be resolved before the AI sees them.
+
+use super::types::{BatchMatchResult, CandidateWork};
+use crate::models::AnimeWork;
+use crate::text::normalize::match_key;
+use chrono::NaiveDate;
+
+/// 低于该分数的最佳候选不视为匹配
+const MIN_LOCAL_SCORE: f32 = 0.3;
+/// 放送日期相差超过该天数时日期得分为0
+const DATE_TOLERANCE_DAYS: f32 = 90.0;
+/// 放送日期相差在该天数以内时日期得分为满分
+const DATE_EXACT_DAYS: f32 = 7.0;
+/// 标题相似度在总分中的权重
+const TITLE_WEIGHT: f32 = 0.75;
+/// 放送日期接近程度在总分中的权重
+const DATE_WEIGHT: f32 = 0.2;
+/// Bangumi排名在总分中的权重，只用于区分标题和日期相近的候选
+const RANK_WEIGHT: f32 = 0.05;
+/// 排名在该名次之后的作品排名得分为0
+const RANK_FLOOR: f32 = 5000.0;
+/// 缺少日期信息时的总分折扣
+const MISSING_DATE_FACTOR: f32 = 0.9;
+
+/// 单个候选作品的本地评分
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandidateScore {
+    pub bangumi_id: u32,
+    /// 标题/别名的最高相似度
+    pub title: f32,
+    /// 放送日期相差天数，任一方缺少日期时为None
+    pub days_apart: Option<i64>,
+    pub total: f32,
+}
+
+/// 字符二元组的Dice系数
+pub fn bigram_dice(a: &str, b: &str) -> f32 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a.len() < 2 || b.len() < 2 {
+        return if a == b { 1.0 } else { 0.0 };
+    }
+
+    let mut b_bigrams: Vec<(char, char)> = b.windows(2).map(|w| (w[0], w[1])).collect();
+    let a_count = a.len() - 1;
+    let b_count = b_bigrams.len();
+    let mut shared = 0;
+    for bigram in a.windows(2).map(|w| (w[0], w[1])) {
+        if let Some(pos) = b_bigrams.iter().position(|&x| x == bigram) {
+            b_bigrams.swap_remove(pos);
+            shared += 1;
+        }
+    }
+
+    2.0 * shared as f32 / (a_count + b_count) as f32
+}
+
+/// 按字符计算的编辑距离
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// 两个标题的相似度（0-1），规范化后取二元组和编辑距离两种度量中的较高者
+pub fn title_similarity(a: &str, b: &str) -> f32 {
+    let a = match_key(a);
+    let b = match_key(b);
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+
+    let max_len = a.chars().count().max(b.chars().count());
+    let edit_similarity = 1.0 - levenshtein(&a, &b) as f32 / max_len as f32;
+    bigram_dice(&a, &b).max(edit_similarity)
+}
+
+/// 放送日期接近程度（0-1）
+fn date_proximity(days_apart: i64) -> f32 {
+    let days = days_apart.unsigned_abs() as f32;
+    if days <= DATE_EXACT_DAYS {
+        1.0
+    } else {
+        (1.0 - (days - DATE_EXACT_DAYS) / (DATE_TOLERANCE_DAYS - DATE_EXACT_DAYS)).max(0.0)
+    }
+}
+
+/// Bangumi排名得分（0-1），未上榜为0
+fn rank_score(rank: Option<u32>) -> f32 {
+    match rank {
+        Some(rank) if rank > 0 => (1.0 - rank as f32 / RANK_FLOOR).max(0.0),
+        _ => 0.0,
+    }
+}
+
+/// 计算候选作品与源作品的本地评分
+pub fn score_candidate(source: &AnimeWork, candidate: &CandidateWork) -> CandidateScore {
+    let source_titles = std::iter::once(&source.cleaned_title)
+        .chain(std::iter::once(&source.original_title))
+        .chain(source.keywords.iter());
+    let candidate_titles: Vec<&String> = std::iter::once(&candidate.japanese_title)
+        .chain(std::iter::once(&candidate.chinese_title))
+        .chain(candidate.aliases.iter())
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let title = source_titles
+        .flat_map(|s| candidate_titles.iter().map(move |c| title_similarity(s, c)))
+        .fold(0.0_f32, f32::max);
+
+    let candidate_date = candidate
+        .air_date
+        .as_deref()
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+    let days_apart = match (source.air_date, candidate_date) {
+        (Some(a), Some(b)) => Some((a - b).num_days()),
+        _ => None,
+    };
+
+    let rank = RANK_WEIGHT * rank_score(candidate.rank);
+    let total = match days_apart {
+        Some(days) => TITLE_WEIGHT * title + DATE_WEIGHT * date_proximity(days) + rank,
+        None => (TITLE_WEIGHT * title + rank) / (TITLE_WEIGHT + RANK_WEIGHT) * MISSING_DATE_FACTOR,
+    };
+
+    CandidateScore {
+        bangumi_id: candidate.bangumi_id,
+        title,
+        days_apart,
+        total,
+    }
+}
+
+/// 对所有候选作品评分，按总分从高到低排序
+pub fn rank_candidates(source: &AnimeWork, candidates: &[CandidateWork]) -> Vec<CandidateScore> {
+    let mut scores: Vec<CandidateScore> = candidates
+        .iter()
+        .map(|candidate| score_candidate(source, candidate))
+        .collect();
+    scores.sort_by(|a, b| b.total.total_cmp(&a.total));
+    scores
+}
+
+/// 不调用AI，直接用本地评分匹配作品
+///
+/// 返回结果的置信度即最佳候选的总分；没有候选作品时返回None。
+pub fn local_match(source: &AnimeWork, candidates: &[CandidateWork]) -> Option<BatchMatchResult> {
+    let best = rank_candidates(source, candidates).into_iter().next()?;
+
+    let date_note = match best.days_apart {
+        Some(days) => format!("放送日期相差 {} 天", days.abs()),
+        None => "缺少放送日期".to_string(),
+    };
+
+    Some(BatchMatchResult {
+        source_index: 0,
+        matched_bangumi_id: (best.total >= MIN_LOCAL_SCORE).then_some(best.bangumi_id),
+        confidence: best.total,
+        reasoning: format!("本地匹配: 标题相似度 {:.2}，{}", best.title, date_note),
+    })
+}
+
+/// 为候选作品填写本地评分，按评分从高到低排序并只保留前 `max_candidates` 个
+pub fn prune_candidates(source: &AnimeWork, candidates: &mut Vec<CandidateWork>, max_candidates: usize) {
+    for candidate in candidates.iter_mut() {
+        candidate.score = Some(score_candidate(source, candidate).total);
+    }
+    candidates.sort_by(|a, b| b.score.unwrap_or(0.0).total_cmp(&a.score.unwrap_or(0.0)));
+    candidates.truncate(max_candidates.max(1));
+}
+
+/// 最佳候选评分不低于 `min_score` 且领先第二名至少 `margin` 时，直接作为匹配结果
+///
+/// 候选作品需已经过 [`prune_candidates`] 评分排序。
+pub fn dominant_match(candidates: &[CandidateWork], min_score: f32, margin: f32) -> Option<BatchMatchResult> {
+    let best = candidates.first()?;
+    let best_score = best.score?;
+    let runner_up = candidates.get(1).and_then(|c| c.score).unwrap_or(0.0);
+    if best_score < min_score || best_score - runner_up < margin {
+        return None;
+    }
+
+    Some(BatchMatchResult {
+        source_index: 0,
+        matched_bangumi_id: Some(best.bangumi_id),
+        confidence: best_score,
+        reasoning: format!("本地评分 {:.2} 明显领先其他候选（次高 {:.2}），未调用AI", best_score, runner_up),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::AnimeWork;
+    use crate::test_support::candidate;
+
+    #[test]
+    fn test_local_fuzzy_matching() {
+        use crate::ai::object_matcher::fuzzy::{levenshtein, local_match, title_similarity};
+
+        assert_eq!(levenshtein("キャッツアイ", "キャッツ・アイ"), 1);
+        assert_eq!(title_similarity("キャッツ・アイ", "キャッツ アイ"), 1.0);
+        assert!(title_similarity("青のミブロ 第二期", "青のミブロ") > title_similarity("青のミブロ 第二期", "薬屋のひとりごと"));
+
+        let source = AnimeWork {
+            original_title: "青のミブロ 第二期「芹沢暗殺編」".to_string(),
+            cleaned_title: "青のミブロ 第二期 芹沢暗殺編".to_string(),
+            air_date: chrono::NaiveDate::from_ymd_opt(2025, 12, 20),
+            keywords: vec![],
+            ..Default::default()
+        };
+
+        // 标题相近时放送日期决定结果
+        let result = local_match(
+            &source,
+            &[
+                candidate(454630, "青のミブロ", Some("2024-10-19")),
+                candidate(560000, "青のミブロ 第二期 芹沢暗殺編", Some("2025-12-20")),
+                candidate(1, "薬屋のひとりごと", Some("2025-12-20")),
+            ],
+        )
+        .expect("有候选作品时应返回结果");
+        assert_eq!(result.matched_bangumi_id, Some(560000));
+        assert!(result.confidence > 0.9);
+
+        // 全部候选都不相似时不匹配
+        let result = local_match(&source, &[candidate(1, "Frieren", Some("2023-09-29"))]).unwrap();
+        assert_eq!(result.matched_bangumi_id, None);
+        assert!(local_match(&source, &[]).is_none());
+    }
+
+    #[test]
+    fn test_candidate_pruning_and_dominance() {
+        use crate::ai::object_matcher::CandidateWork;
+        use crate::ai::object_matcher::fuzzy::{dominant_match, prune_candidates};
+
+        let source = AnimeWork {
+            original_title: "SPY×FAMILY Season 3".to_string(),
+            cleaned_title: "SPY×FAMILY Season 3".to_string(),
+            air_date: chrono::NaiveDate::from_ymd_opt(2025, 10, 4),
+            keywords: vec!["间谍过家家 第三季".to_string()],
+            ..Default::default()
+        };
+        let ranked = |id: u32, title: &str, air_date: &str, rank: Option<u32>| CandidateWork {
+            rank,
+            ..candidate(id, title, Some(air_date))
+        };
+        let mut candidates = vec![
+            ranked(1, "ぼっち・ざ・ろっく！", "2022-10-08", Some(20)),
+            ranked(2, "SPY×FAMILY", "2022-04-09", Some(300)),
+            ranked(3, "SPY×FAMILY Season 3", "2025-10-04", None),
+            ranked(4, "劇場版 SPY×FAMILY CODE: White", "2023-12-22", Some(900)),
+        ];
+
+        prune_candidates(&source, &mut candidates, 3);
+        assert_eq!(candidates.len(), 3);
+        assert_eq!(candidates[0].bangumi_id, 3);
+        assert!(candidates.windows(2).all(|w| w[0].score >= w[1].score));
+        assert!(candidates.iter().all(|c| c.bangumi_id != 1));
+
+        let dominant = dominant_match(&candidates, 0.85, 0.25).expect("第三季应明显领先");
+        assert_eq!(dominant.matched_bangumi_id, Some(3));
+
+        // 领先幅度不足时交给AI判断
+        let mut close = vec![
+            ranked(2, "SPY×FAMILY", "2025-10-04", Some(300)),
+            ranked(3, "SPY×FAMILY Season 3", "2025-10-04", None),
+        ];
+        prune_candidates(&source, &mut close, 8);
+        assert!(dominant_match(&close, 0.85, 0.25).is_none());
+    }
+}
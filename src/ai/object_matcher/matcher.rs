@@ -1,60 +1,40 @@
-use super::types::{CandidateWork, BatchMatchResponse};
-use crate::models::{AnimeWork, AiConfig, AiProvider, AiRequest, AiMessage, AiResponse};
-use std::env;
+use super::fuzzy::{dominant_match, local_match};
+use super::types::{CandidateWork, BatchMatchResponse, BatchMatchResult};
+use crate::ai::budget::estimate_matching_usage;
+use crate::ai::prompts;
+use crate::models::{AnimeWork, AiConfig, AiRequest, AiMessage, AiResponse, MatchMode, MatchSettings};
+use futures::stream::{self, StreamExt};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 
 /// 批量匹配多个源作品与候选作品
 /// 将多个匹配请求合并为一个AI请求，显著减少API调用次数
+///
+/// 返回每个源作品的AI匹配结果（含置信度和理由），未得到AI回复的作品为None。
+/// 结果未经置信度过滤，调用方通过 `BatchMatchResult::accepted_id` 决定是否采纳。
 pub async fn batch_match_works_with_ai(
     source_works: &[&AnimeWork],
     candidate_works_map: &[&Vec<CandidateWork>],
     ai_config: &AiConfig,
-) -> Result<Vec<Option<u32>>, Box<dyn std::error::Error>> {
+) -> Result<Vec<Option<BatchMatchResult>>, Box<dyn std::error::Error>> {
     if source_works.len() != candidate_works_map.len() {
         return Err("源作品数量和候选作品映射数量不匹配".into());
     }
 
-    let api_key = match ai_config.provider {
-        AiProvider::DeepSeek => env::var("DEEPSEEK_API_KEY")?,
-    };
+    let api_key = ai_config.api_key()?;
 
+    // 预算不足时不调用AI，改用本地匹配
+    let candidate_count = candidate_works_map.iter().map(|c| c.len()).sum();
+    let estimate = estimate_matching_usage(source_works.len(), candidate_count, source_works.len());
+    if !ai_config.budget.allow(&estimate)? {
+        return Ok(match_works_locally(source_works, candidate_works_map));
+    }
 
     let batch_tasks_content = format_batch_match_tasks(source_works, candidate_works_map);
 
-    let prompt = format!(
-        r#"请为以下多个独立的匹配任务找到最合适的Bangumi作品。每个任务都是完全独立的，请不要混淆不同任务之间的信息。
-
-重要规则：
-1. 每个任务都是独立的，只考虑该任务内的源作品和候选作品
-2. 不要将任务A的关键词与任务B的候选作品匹配
-3. 每个任务必须单独评估，互不影响
-
-任务列表：
-{}
-
-匹配标准（对每个任务独立应用）：
-- 标题语义相似性（包括特殊符号、季度表示差异）
-- 放映时间的接近程度（前后30天内）
-- 关键词与候选作品标题/别名的匹配度
-- 是否为同一作品的不同季度
-
-返回格式要求：
-- 必须为每个任务返回一个结果，即使没有匹配也要返回null
-- confidence必须基于该任务内的信息独立计算
-- reasoning必须说明为什么选择这个匹配（或为什么不匹配）
-
-请返回JSON格式：
-{{
-  "matches": [
-    {{"source_index": 0, "matched_bangumi_id": <ID或null>, "confidence": <0-1的置信度>, "reasoning": "匹配理由"}},
-    {{"source_index": 1, "matched_bangumi_id": <ID或null>, "confidence": <0-1的置信度>, "reasoning": "匹配理由"}},
-    // ... 确保每个任务都有对应的结果
-  ]
-}}
-
-注意：如果没有高度匹配（confidence > 0.7），请返回null。"#,
-        batch_tasks_content
-    );
+    let prompt = ai_config
+        .prompts
+        .render(prompts::BATCH_MATCHING, &[("tasks", &batch_tasks_content)]);
 
     let request = AiRequest {
         model: ai_config.model.clone(),
@@ -98,6 +78,8 @@ pub async fn batch_match_works_with_ai(
         }
     };
 
+    ai_config.budget.record(api_response.usage.as_ref());
+
     if let Some(choice) = api_response.choices.first() {
         let content = choice.message.content.trim();
 
@@ -114,8 +96,9 @@ pub async fn batch_match_works_with_ai(
             // 将匹配结果按源作品索引排序
             let mut results = vec![None; source_works.len()];
             for match_result in batch_response.matches {
-                if match_result.source_index < source_works.len() && match_result.confidence > 0.7 {
-                    results[match_result.source_index] = match_result.matched_bangumi_id;
+                if match_result.source_index < source_works.len() {
+                    let source_index = match_result.source_index;
+                    results[source_index] = Some(match_result);
                 }
             }
             return Ok(results);
@@ -126,53 +109,144 @@ pub async fn batch_match_works_with_ai(
     Ok(vec![None; source_works.len()])
 }
 
-/// 批量处理多个搜索任务，自动分批处理以避免token超限
-pub async fn batch_process_searches(
+/// 按配置的匹配方式处理所有搜索任务，返回结果的顺序与 `search_tasks` 一致
+pub async fn match_search_tasks(
     search_tasks: &[(AnimeWork, Vec<CandidateWork>)],
     ai_config: &AiConfig,
+    settings: &MatchSettings,
     batch_size: usize,
     progress_bar: Option<&indicatif::ProgressBar>,
-) -> Result<Vec<Option<u32>>, Box<dyn std::error::Error>> {
-    let mut all_results = Vec::new();
-
-
-    // 分批处理
-    for (batch_index, chunk) in search_tasks.chunks(batch_size).enumerate() {
-        if let Some(pb) = progress_bar {
-            pb.set_message(format!(
-                "处理批次 {}/{} ({}个搜索任务)",
-                batch_index + 1,
-                search_tasks.len().div_ceil(batch_size),
-                chunk.len()
-            ));
+) -> Result<Vec<Option<BatchMatchResult>>, Box<dyn std::error::Error>> {
+    // 本地评分明显领先的候选直接采纳；混合模式还会用本地评分解决高分匹配，其余作品交给AI
+    let mut results = vec![None; search_tasks.len()];
+    let mut ai_task_indices = Vec::new();
+    for (index, (source, candidates)) in search_tasks.iter().enumerate() {
+        if settings.mode == MatchMode::Local {
+            results[index] = local_match(source, candidates);
+            continue;
         }
-
-        match batch_match_works_with_ai(
-            &chunk.iter().map(|(source, _)| source).collect::<Vec<_>>(),
-            &chunk.iter().map(|(_, candidates)| candidates).collect::<Vec<_>>(),
-            ai_config
-        ).await {
-            Ok(batch_results) => {
-                all_results.extend(batch_results);
-                log::debug!("成功处理批次 {}，处理了 {} 个任务", batch_index + 1, chunk.len());
-            }
-            Err(e) => {
-                log::error!("处理批次 {} 时发生错误: {}", batch_index + 1, e);
-                // 如果AI匹配失败，为这个批次的所有任务返回None
-                all_results.extend(vec![None; chunk.len()]);
+        if let Some(dominant) = dominant_match(candidates, settings.dominant_score, settings.dominant_margin) {
+            results[index] = Some(dominant);
+            continue;
+        }
+        match local_match(source, candidates) {
+            Some(local) if settings.mode == MatchMode::Hybrid && local.confidence >= settings.prefilter_threshold => {
+                results[index] = Some(local);
             }
+            _ => ai_task_indices.push(index),
         }
+    }
 
-        // 更新进度条
-        if let Some(pb) = progress_bar {
-            pb.inc(chunk.len() as u64);
-        }
+    let resolved_locally = search_tasks.len() - ai_task_indices.len();
+    if let Some(pb) = progress_bar {
+        pb.inc(resolved_locally as u64);
+    }
+    if ai_task_indices.is_empty() {
+        return Ok(results);
+    }
+    if resolved_locally > 0 {
+        log::info!(
+            "本地预匹配解决 {} 个作品，剩余 {} 个交给AI匹配",
+            resolved_locally,
+            ai_task_indices.len()
+        );
+    }
+
+    let ai_tasks: Vec<(AnimeWork, Vec<CandidateWork>)> = ai_task_indices
+        .iter()
+        .map(|&index| search_tasks[index].clone())
+        .collect();
+    let candidate_count = ai_tasks.iter().map(|(_, candidates)| candidates.len()).sum();
+    let matching_estimate = estimate_matching_usage(ai_tasks.len(), candidate_count, batch_size);
+    ai_config.budget.add_estimate(matching_estimate);
+    log::info!(
+        "预计AI匹配用量: {} tokens，约 {:.4} {}",
+        matching_estimate.total(),
+        ai_config.budget.cost_of(&matching_estimate),
+        ai_config.budget.pricing.currency
+    );
 
-        // 添加延迟以避免API限制
-        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    let ai_results = batch_process_searches(&ai_tasks, ai_config, batch_size, progress_bar).await?;
+    for (index, result) in ai_task_indices.into_iter().zip(ai_results) {
+        results[index] = result;
     }
 
-    Ok(all_results)
+    Ok(results)
+}
+
+/// 用本地评分匹配一批作品
+fn match_works_locally(
+    source_works: &[&AnimeWork],
+    candidate_works_map: &[&Vec<CandidateWork>],
+) -> Vec<Option<BatchMatchResult>> {
+    source_works
+        .iter()
+        .zip(candidate_works_map)
+        .enumerate()
+        .map(|(index, (source, candidates))| {
+            local_match(source, candidates).map(|result| BatchMatchResult {
+                source_index: index,
+                ..result
+            })
+        })
+        .collect()
+}
+
+/// 批量处理多个搜索任务，自动分批处理以避免token超限
+///
+/// 各批次按 `ai_config.concurrency` 并发发送，共享同一个速率限制器，
+/// 返回结果的顺序与 `search_tasks` 一致。
+pub async fn batch_process_searches(
+    search_tasks: &[(AnimeWork, Vec<CandidateWork>)],
+    ai_config: &AiConfig,
+    batch_size: usize,
+    progress_bar: Option<&indicatif::ProgressBar>,
+) -> Result<Vec<Option<BatchMatchResult>>, Box<dyn std::error::Error>> {
+    let total_batches = search_tasks.len().div_ceil(batch_size);
+    let completed_batches = AtomicUsize::new(0);
+
+    let batch_results: Vec<Vec<Option<BatchMatchResult>>> = stream::iter(search_tasks.chunks(batch_size).enumerate())
+        .map(|(batch_index, chunk)| {
+            let completed_batches = &completed_batches;
+            async move {
+                ai_config.rate_limiter.acquire().await;
+
+                let results = match batch_match_works_with_ai(
+                    &chunk.iter().map(|(source, _)| source).collect::<Vec<_>>(),
+                    &chunk.iter().map(|(_, candidates)| candidates).collect::<Vec<_>>(),
+                    ai_config
+                ).await {
+                    Ok(batch_results) => {
+                        log::debug!("成功处理批次 {}，处理了 {} 个任务", batch_index + 1, chunk.len());
+                        batch_results
+                    }
+                    Err(e) => {
+                        log::error!("处理批次 {} 时发生错误: {}", batch_index + 1, e);
+                        // 如果AI匹配失败，为这个批次的所有任务返回None
+                        vec![None; chunk.len()]
+                    }
+                };
+
+                // 更新进度条
+                let completed = completed_batches.fetch_add(1, Ordering::SeqCst) + 1;
+                if let Some(pb) = progress_bar {
+                    pb.inc(chunk.len() as u64);
+                    pb.set_message(format!("已完成批次 {}/{}", completed, total_batches));
+                }
+
+                results
+            }
+        })
+        .buffered(ai_config.concurrency.max(1))
+        .collect()
+        .await;
+
+    // 批次错误已按未匹配处理，但超出预算且配置为停止时需要中断运行
+    if ai_config.budget.should_stop() {
+        return Err("AI预算已用尽，停止运行".into());
+    }
+
+    Ok(batch_results.into_iter().flatten().collect())
 }
 
 fn format_batch_match_tasks(source_works: &[&AnimeWork], candidate_works_map: &[&Vec<CandidateWork>]) -> String {
@@ -215,14 +289,29 @@ fn format_candidate_works(candidate_works: &[CandidateWork]) -> String {
                     .join("、")
             };
 
+            let score_display = candidate
+                .score
+                .map(|score| format!(" 本地评分:『{:.2}』", score))
+                .unwrap_or_default();
+
+            let mut info_display = String::new();
+            if let Some(count) = candidate.info.episode_count {
+                info_display.push_str(&format!(" 话数:『{}』", count));
+            }
+            if !candidate.info.studios.is_empty() {
+                info_display.push_str(&format!(" 动画制作:『{}』", candidate.info.studios.join("、")));
+            }
+
             format!(
-                "{}. [ID: {}] 日文标题:『{}』 中文标题:『{}』 放映时间:『{}』 别名: {}",
+                "{}. [ID: {}] 日文标题:『{}』 中文标题:『{}』 放映时间:『{}』 别名: {}{}{}",
                 i + 1,
                 candidate.bangumi_id,
                 candidate.japanese_title,
                 candidate.chinese_title,
                 candidate.air_date.as_deref().unwrap_or("未知"),
-                aliases_display
+                aliases_display,
+                info_display,
+                score_display
             )
         })
         .collect::<Vec<_>>()
@@ -235,8 +324,8 @@ pub async fn match_works_with_ai(
     source_work: &AnimeWork,
     candidate_works: &[CandidateWork],
     ai_config: &AiConfig,
-) -> Result<Option<u32>, Box<dyn std::error::Error>> {
+) -> Result<Option<BatchMatchResult>, Box<dyn std::error::Error>> {
     let candidate_works_vec = candidate_works.to_vec();
     let results = batch_match_works_with_ai(&[source_work], &[&candidate_works_vec], ai_config).await?;
-    Ok(results.first().copied().flatten())
+    Ok(results.into_iter().next().flatten())
 }
\ No newline at end of file
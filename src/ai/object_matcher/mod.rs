@@ -3,12 +3,13 @@
 //! This module provides intelligent matching between source works from kansou
 //! and candidate works from Bangumi using AI semantic understanding.
 
+pub mod fuzzy;
 pub mod matcher;
 pub mod types;
 pub mod utils;
 
 // Re-export the main API
 #[allow(unused_imports)]
-pub use matcher::{batch_match_works_with_ai, batch_process_searches};
+pub use matcher::{batch_match_works_with_ai, batch_process_searches, match_search_tasks};
 #[allow(unused_imports)]
 pub use types::*;
\ No newline at end of file
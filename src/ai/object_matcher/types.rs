@@ -1,4 +1,5 @@
-use crate::models::BangumiSubject;
+use crate::meta_providers::bangumi::infobox::parse_infobox;
+use crate::models::{BangumiSubject, InfoboxInfo};
 use serde::{Deserialize, Serialize};
 
 // SourceWork已被移除，直接使用AnimeWork
@@ -10,7 +11,14 @@ pub struct CandidateWork {
     pub chinese_title: String,
     pub aliases: Vec<String>,
     pub air_date: Option<String>,
+    /// 本地相关度评分，剪枝候选作品时计算
     pub score: Option<f32>,
+    /// Bangumi排名，未上榜时为None
+    #[serde(default)]
+    pub rank: Option<u32>,
+    /// infobox中的常用字段
+    #[serde(default)]
+    pub info: InfoboxInfo,
 }
 
 
@@ -19,7 +27,10 @@ pub struct BatchMatchResponse {
     pub matches: Vec<BatchMatchResult>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// AI匹配结果的默认采纳阈值，置信度需高于该值
+pub const DEFAULT_ACCEPT_THRESHOLD: f32 = 0.7;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct BatchMatchResult {
     pub source_index: usize,
     pub matched_bangumi_id: Option<u32>,
@@ -27,21 +38,58 @@ pub struct BatchMatchResult {
     pub reasoning: String,
 }
 
+impl BatchMatchResult {
+    /// 置信度高于阈值时返回匹配的Bangumi ID
+    pub fn accepted_id(&self, threshold: f32) -> Option<u32> {
+        self.matched_bangumi_id.filter(|_| self.confidence > threshold)
+    }
+}
+
 // SourceWork已被移除，直接使用AnimeWork
 
 impl From<&BangumiSubject> for CandidateWork {
     fn from(subject: &BangumiSubject) -> Self {
-        // 从infobox中提取放映时间和别名
+        // 从infobox中提取放映时间、别名和其他常用字段
+        let info = parse_infobox(&subject.infobox);
         let air_date = super::utils::extract_air_date_from_subject(subject);
-        let aliases = crate::meta_providers::bangumi::extract_aliases_from_infobox(&subject.infobox);
 
         CandidateWork {
             bangumi_id: subject.id,
             japanese_title: subject.name.clone(),
             chinese_title: subject.name_cn.clone(),
-            aliases,
+            aliases: info.aliases.clone(),
             air_date: air_date.map(|d| d.to_string()),
             score: None,
+            rank: subject.rating.as_ref().map(|r| r.rank).filter(|&rank| rank > 0),
+            info,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_match_confidence_is_kept() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::ai::object_matcher::{BatchMatchResult, DEFAULT_ACCEPT_THRESHOLD};
+        use crate::models::BangumiResult;
+
+        let weak = BatchMatchResult {
+            source_index: 0,
+            matched_bangumi_id: Some(454630),
+            confidence: 0.6,
+            reasoning: "标题相似但季度不同".to_string(),
+        };
+        assert_eq!(weak.accepted_id(DEFAULT_ACCEPT_THRESHOLD), None);
+        assert_eq!(weak.accepted_id(0.5), Some(454630));
+
+        // 旧版本的 bangumi_results.json 没有匹配信息字段，仍应能读取
+        let legacy: BangumiResult = serde_json::from_str(
+            r#"{"original_title": "SANDA【サンダ】", "cleaned_title": "SANDA", "bangumi_id": 503303,
+                "chinese_name": null, "aliases": [], "air_date": "2025-10-03", "keywords": []}"#,
+        )?;
+        assert_eq!(legacy.match_confidence, None);
+        assert_eq!(legacy.candidate_count, 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+//! Named prompt templates for the AI stages
+//!
+//! Templates use `{{variable}}` placeholders. The built-in defaults can be
+//! replaced per template by dropping `<name>.txt` into the prompts directory
+//! (by default `<config dir>/prompts`).
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 表格选择提示词，变量: description, tables
+pub const TABLE_SELECTION: &str = "table_selection";
+/// 标题清理和关键字生成提示词，变量: works
+pub const TITLE_CLEANING: &str = "title_cleaning";
+/// 批量作品匹配提示词，变量: tasks
+pub const BATCH_MATCHING: &str = "batch_matching";
+
+const DEFAULT_TABLE_SELECTION: &str = r#"用户提供的描述是: '{{description}}'
+
+以下是网页中找到的表格标题:
+{{tables}}
+
+请根据用户描述，判断哪个表格标题最相关。请返回表格的序号（从0开始）。
+
+请返回JSON格式：{"table_index": 数字}"#;
+
+const DEFAULT_TITLE_CLEANING: &str = r#"以下是需要处理的动画作品列表：
+
+{{works}}
+
+请为每个作品执行以下操作：
+1. 清理标题，去除无用信息如【日本語吹替版】等，但保留季号信息和副标题
+   - 重要：副标题如『』、【】、（）、《》、「」中的内容都是重要信息，必须保留
+   - 例如：'青のミブロ 第二期「芹沢暗殺編」' 中的 '「芹沢暗殺編」' 必须保留
+   - 已给出“已清理标题”的作品无需清理，直接将其作为cleaned_title
+2. 生成3-5个搜索关键字 - 请包含：
+   - 常见中文译名
+   - 英文名称
+   - 其他常见的名称（如原作名、简称）
+   特别提醒：
+   - 主标题和副标题同等重要，至少有一个关键字必须同时包含主副标题（用半角空格分割）
+   - 对于经典作品的重制/新作，请包含原版作品的各种常见名称变体
+   - 对于经典作品如'キャッツ・アイ'，请包含'猫眼三姐妹'等常见中文译名
+   - 日文原标题、中点・/♥/☆等符号的变体、季号的不同写法以及副标题带/不带括号的变体会由程序自动生成，无需重复列出
+
+请返回JSON格式：{"works": [{"original_title": "原标题", "cleaned_title": "清理后标题", "keywords": ["关键词1", "关键词2"]}, ...]}"#;
+
+const DEFAULT_BATCH_MATCHING: &str = r#"请为以下多个独立的匹配任务找到最合适的Bangumi作品。每个任务都是完全独立的，请不要混淆不同任务之间的信息。
+
+重要规则：
+1. 每个任务都是独立的，只考虑该任务内的源作品和候选作品
+2. 不要将任务A的关键词与任务B的候选作品匹配
+3. 每个任务必须单独评估，互不影响
+
+任务列表：
+{{tasks}}
+
+匹配标准（对每个任务独立应用）：
+- 标题语义相似性（包括特殊符号、季度表示差异）
+- 放映时间的接近程度（前后30天内）
+- 关键词与候选作品标题/别名的匹配度
+- 是否为同一作品的不同季度
+- 候选作品的本地评分（综合标题相似度、放映时间和Bangumi排名，仅供参考）
+
+返回格式要求：
+- 必须为每个任务返回一个结果，即使没有匹配也要返回null
+- confidence必须基于该任务内的信息独立计算
+- reasoning必须说明为什么选择这个匹配（或为什么不匹配）
+
+请返回JSON格式：
+{
+  "matches": [
+    {"source_index": 0, "matched_bangumi_id": <ID或null>, "confidence": <0-1的置信度>, "reasoning": "匹配理由"},
+    {"source_index": 1, "matched_bangumi_id": <ID或null>, "confidence": <0-1的置信度>, "reasoning": "匹配理由"},
+    // ... 确保每个任务都有对应的结果
+  ]
+}
+
+注意：即使匹配程度不高，也请给出最可能的候选作品和如实的置信度；只有当所有候选作品都明显不是同一作品时才返回null。"#;
+
+/// 各模板名称及其必需的变量
+const TEMPLATE_VARIABLES: &[(&str, &[&str])] = &[
+    (TABLE_SELECTION, &["description", "tables"]),
+    (TITLE_CLEANING, &["works"]),
+    (BATCH_MATCHING, &["tasks"]),
+];
+
+/// 提示词模板集合
+#[derive(Debug, Clone)]
+pub struct PromptTemplates {
+    templates: HashMap<String, String>,
+}
+
+impl Default for PromptTemplates {
+    fn default() -> Self {
+        let templates = [
+            (TABLE_SELECTION, DEFAULT_TABLE_SELECTION),
+            (TITLE_CLEANING, DEFAULT_TITLE_CLEANING),
+            (BATCH_MATCHING, DEFAULT_BATCH_MATCHING),
+        ]
+        .into_iter()
+        .map(|(name, template)| (name.to_string(), template.to_string()))
+        .collect();
+
+        Self { templates }
+    }
+}
+
+impl PromptTemplates {
+    /// 加载默认模板，并用目录中的 `<模板名>.txt` 覆盖对应模板
+    ///
+    /// 目录不存在时直接使用默认模板；缺少必需变量的覆盖文件会被忽略。
+    pub fn load(dir: &Path) -> Self {
+        let mut prompts = Self::default();
+
+        for (name, variables) in TEMPLATE_VARIABLES {
+            let path = dir.join(format!("{}.txt", name));
+            let Ok(template) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+
+            let missing: Vec<&str> = variables
+                .iter()
+                .filter(|var| !template.contains(&format!("{{{{{}}}}}", var)))
+                .copied()
+                .collect();
+            if !missing.is_empty() {
+                log::warn!(
+                    "提示词模板 {} 缺少变量 {:?}，继续使用默认模板",
+                    path.display(),
+                    missing
+                );
+                continue;
+            }
+
+            log::info!("使用自定义提示词模板: {}", path.display());
+            prompts.templates.insert(name.to_string(), template);
+        }
+
+        prompts
+    }
+
+    /// 用变量渲染指定模板
+    pub fn render(&self, name: &str, variables: &[(&str, &str)]) -> String {
+        let mut rendered = self.templates.get(name).cloned().unwrap_or_default();
+        for (key, value) in variables {
+            rendered = rendered.replace(&format!("{{{{{}}}}}", key), value);
+        }
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_prompt_template_overrides() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::ai::prompts::{self, PromptTemplates};
+
+        // 默认模板应替换所有变量
+        let defaults = PromptTemplates::default();
+        let rendered = defaults.render(
+            prompts::TABLE_SELECTION,
+            &[("description", "2025年10月新番"), ("tables", "[0] 标题: 2025秋アニメ")],
+        );
+        assert!(rendered.contains("2025年10月新番"));
+        assert!(rendered.contains("[0] 标题: 2025秋アニメ"));
+        assert!(!rendered.contains("{{"));
+
+        // 覆盖文件：合法的模板生效，缺少变量的模板被忽略
+        let dir = std::env::temp_dir().join(format!("brb_prompts_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("title_cleaning.txt"), "Clean these titles:\n{{works}}")?;
+        std::fs::write(dir.join("batch_matching.txt"), "no variables here")?;
+
+        let templates = PromptTemplates::load(&dir);
+        assert_eq!(
+            templates.render(prompts::TITLE_CLEANING, &[("works", "作品A")]),
+            "Clean these titles:\n作品A"
+        );
+        assert!(
+            templates
+                .render(prompts::BATCH_MATCHING, &[("tasks", "任务")])
+                .contains("任务列表")
+        );
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
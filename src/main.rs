@@ -4,10 +4,16 @@ mod ai;
 mod logger;
 mod meta_providers;
 mod models;
+mod overrides;
+mod review;
 mod rules;
 mod sites;
+mod text;
 mod utils;
 
+#[cfg(test)]
+mod test_support;
+
 use crate::models::Task;
 
 #[tokio::main]
@@ -39,6 +45,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    // 缓存维护命令：处理后直接退出
+    if handle_cache_args(&task)? {
+        return Ok(());
+    }
+
     log::info!("任务描述: {}", task.description);
     log::info!("站点: {}", task.site);
 
@@ -47,6 +58,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             log::info!("开始处理kansou站点...");
             sites::kansou::process_kansou_site(&task).await?;
             log::info!("kansou站点处理完成");
+        }
+        models::SiteType::BangumiCollection => {
+            log::info!("开始处理Bangumi收藏...");
+            sites::bangumi_collection::process_bangumi_collection(&task).await?;
+            log::info!("Bangumi收藏处理完成");
         } // 未来添加其他站点支持
           // models::SiteType::ModelScope => {
           //     log::info!("开始处理modelscope站点...");
@@ -64,10 +80,40 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// 处理 `--clear-cache` 和 `--invalidate <关键字或条目ID>` 参数，返回是否执行了缓存命令
+fn handle_cache_args(task: &Task) -> Result<bool, Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let clear = args.iter().any(|arg| arg == "--clear-cache");
+    let patterns: Vec<&String> = args
+        .windows(2)
+        .filter(|pair| pair[0] == "--invalidate")
+        .map(|pair| &pair[1])
+        .collect();
+    if !clear && patterns.is_empty() {
+        return Ok(false);
+    }
+
+    let Some(cache) = meta_providers::bangumi::cache::BangumiCache::from_settings(&task.bangumi) else {
+        log::warn!("Bangumi缓存未启用（cache_ttl_hours 为 0），无需清理");
+        return Ok(true);
+    };
+
+    if clear {
+        cache.clear();
+        log::info!("已清空Bangumi缓存");
+    }
+    for pattern in patterns {
+        let removed = cache.invalidate(pattern);
+        log::info!("已删除与 {} 相关的 {} 条缓存", pattern, removed);
+    }
+    cache.save()?;
+    Ok(true)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::meta_providers::bangumi::{search_bangumi_for_works, search_bangumi_with_keyword};
-    use crate::models::AnimeWork;
+    use crate::meta_providers::bangumi::{BangumiClient, search_bangumi_for_works, search_bangumi_with_window};
+    use crate::models::{AiConfig, AirDateWindow, AnimeWork, MatchSettings};
     use crate::logger;
     use crate::sites::kansou;
 
@@ -89,6 +135,7 @@ mod tests {
                 "青之炎 第二期".to_string(),
                 "青之壬生狼 第二季".to_string(),
             ],
+            ..Default::default()
         };
 
         log::info!("测试作品: {}", test_work.cleaned_title);
@@ -96,13 +143,13 @@ mod tests {
         log::debug!("关键词列表: {:?}", test_work.keywords);
 
         // 测试搜索
-        let results = search_bangumi_for_works(&[test_work]).await?;
+        let results = search_bangumi_for_works(&BangumiClient::default(), &[test_work], &AiConfig::deepseek(), &MatchSettings::default()).await?;
 
         if let Some(result) = results.first() {
             log::debug!("搜索结果: {:?}", result);
-            if result.bangumi_id.is_some() {
+            if let Some(bangumi_id) = result.bangumi_id {
                 log::info!("✅ 成功找到Bangumi信息!");
-                log::info!("   Bangumi ID: {}", result.bangumi_id.unwrap());
+                log::info!("   Bangumi ID: {}", bangumi_id);
                 log::info!("   中文名称: {:?}", result.chinese_name);
                 log::info!("   别名: {:?}", result.aliases);
             } else {
@@ -122,12 +169,13 @@ mod tests {
     #[tokio::test]
     async fn test_specific_keywords() -> Result<(), Box<dyn std::error::Error>> {
         log::info!("🧪 测试特定关键词的Bangumi搜索...");
-        let client = reqwest::Client::new();
+        let client = BangumiClient::default();
+        let window = AirDateWindow::from_days(MatchSettings::default().air_date_window_days);
 
         // 测试 "青のミブロ" 关键词
         log::info!("📝 测试关键词: 青のミブロ 第二期 芹沢暗殺編");
         let subjects =
-            search_bangumi_with_keyword(&client, "青のミブロ 第二期 芹沢暗殺編", &None).await?;
+            search_bangumi_with_window(&client, "青のミブロ 第二期 芹沢暗殺編", &None, window).await?;
         if !subjects.is_empty() {
             let subject = &subjects[0];
             log::info!("✅ 成功找到作品: {}", subject.name);
@@ -140,7 +188,7 @@ mod tests {
         // 测试 "SHIBUYA HACHI" 关键词
         log::info!("📝 测试关键词: SHIBUYA HACHI 第4クール");
         let subjects =
-            search_bangumi_with_keyword(&client, "SHIBUYA HACHI 第4クール", &None).await?;
+            search_bangumi_with_window(&client, "SHIBUYA HACHI 第4クール", &None, window).await?;
         if !subjects.is_empty() {
             let subject = &subjects[0];
             log::info!("✅ 成功找到作品: {}", subject.name);
@@ -152,7 +200,7 @@ mod tests {
 
         // 测试 "Ao no Miburo" 关键词
         log::info!("📝 测试关键词: Ao no Miburo");
-        let subjects = search_bangumi_with_keyword(&client, "Ao no Miburo", &None).await?;
+        let subjects = search_bangumi_with_window(&client, "Ao no Miburo", &None, window).await?;
         if !subjects.is_empty() {
             let subject = &subjects[0];
             log::info!("✅ 成功找到作品: {}", subject.name);
@@ -164,7 +212,7 @@ mod tests {
 
         // 测试 "SANDA" 关键词
         log::info!("📝 测试关键词: SANDA");
-        let subjects = search_bangumi_with_keyword(&client, "SANDA", &None).await?;
+        let subjects = search_bangumi_with_window(&client, "SANDA", &None, window).await?;
         if !subjects.is_empty() {
             let subject = &subjects[0];
             log::info!("✅ 成功找到作品: {}", subject.name);
@@ -176,7 +224,7 @@ mod tests {
 
         // 测试 "SANDA サンダ" 关键词
         log::info!("📝 测试关键词: SANDA サンダ");
-        let subjects = search_bangumi_with_keyword(&client, "SANDA サンダ", &None).await?;
+        let subjects = search_bangumi_with_window(&client, "SANDA サンダ", &None, window).await?;
         if !subjects.is_empty() {
             let subject = &subjects[0];
             log::info!("✅ 成功找到作品: {}", subject.name);
@@ -189,7 +237,7 @@ mod tests {
         // 测试 "SANDA" 关键词带日期过滤
         log::info!("📝 测试关键词: SANDA (带日期过滤 2025-10-03)");
         let sanda_date = chrono::NaiveDate::from_ymd_opt(2025, 10, 3);
-        let subjects = search_bangumi_with_keyword(&client, "SANDA", &sanda_date).await?;
+        let subjects = search_bangumi_with_window(&client, "SANDA", &sanda_date, window).await?;
         if !subjects.is_empty() {
             let subject = &subjects[0];
             log::info!("✅ 成功找到作品: {}", subject.name);
@@ -220,6 +268,7 @@ mod tests {
                 "青之炎 第二期".to_string(),
                 "青之壬生狼 第二季".to_string(),
             ],
+            ..Default::default()
         };
 
         log::info!("测试作品: {}", test_work.cleaned_title);
@@ -227,13 +276,13 @@ mod tests {
         log::info!("无日期过滤");
 
         // 测试搜索
-        let results = search_bangumi_for_works(&[test_work]).await?;
+        let results = search_bangumi_for_works(&BangumiClient::default(), &[test_work], &AiConfig::deepseek(), &MatchSettings::default()).await?;
 
         if let Some(result) = results.first() {
             log::debug!("搜索结果: {:?}", result);
-            if result.bangumi_id.is_some() {
+            if let Some(bangumi_id) = result.bangumi_id {
                 log::info!("✅ 成功找到Bangumi信息!");
-                log::info!("   Bangumi ID: {}", result.bangumi_id.unwrap());
+                log::info!("   Bangumi ID: {}", bangumi_id);
                 log::info!("   中文名称: {:?}", result.chinese_name);
                 log::info!("   别名: {:?}", result.aliases);
             } else {
@@ -250,7 +299,7 @@ mod tests {
     async fn test_ai_individual_matching() -> Result<(), Box<dyn std::error::Error>> {
         log::info!("🧪 测试AI单个匹配...");
 
-        use crate::ai::object_matcher::{CandidateWork, batch_match_works_with_ai};
+        use crate::ai::object_matcher::{CandidateWork, DEFAULT_ACCEPT_THRESHOLD, batch_match_works_with_ai};
         use crate::meta_providers::bangumi::search_bangumi_for_works;
         use crate::models::{AiConfig, AnimeWork};
 
@@ -263,33 +312,32 @@ mod tests {
             cleaned_title: "破产富豪".to_string(),
             air_date: chrono::NaiveDate::from_ymd_opt(2025, 10, 1),
             keywords: vec!["破产富豪".to_string(), "Bankrupt Billionaire".to_string()],
+            ..Default::default()
         }; // 该数据应该匹配不到任何结果
 
-        let bangumi_results1 = search_bangumi_for_works(&[anime_work1.clone()]).await?;
+        let bangumi_results1 = search_bangumi_for_works(&BangumiClient::default(), std::slice::from_ref(&anime_work1), &ai_config, &MatchSettings::default()).await?;
 
         // 从Bangumi结果中提取候选作品信息
         let candidate_works1: Vec<CandidateWork> = bangumi_results1
             .iter()
             .filter_map(|result| {
-                if let Some(bangumi_id) = result.bangumi_id {
-                    Some(CandidateWork {
-                        bangumi_id,
-                        japanese_title: result.original_title.clone(),
-                        chinese_title: result.chinese_name.clone().unwrap_or_default(),
-                        air_date: result.air_date.map(|d| d.to_string()),
-                        aliases: result.aliases.clone(),
-                        score: None,
-                    })
-                } else {
-                    None
-                }
+                result.bangumi_id.map(|bangumi_id| CandidateWork {
+                    bangumi_id,
+                    japanese_title: result.original_title.clone(),
+                    chinese_title: result.chinese_name.clone().unwrap_or_default(),
+                    air_date: result.air_date.map(|d| d.to_string()),
+                    aliases: result.aliases.clone(),
+                    score: None,
+                    rank: None,
+                    info: Default::default(),
+                })
             })
             .collect();
 
         log::info!("找到 {} 个候选作品", candidate_works1.len());
         let result1 =
             batch_match_works_with_ai(&[&anime_work1], &[&candidate_works1], &ai_config).await?;
-        let result1 = result1.first().copied().flatten();
+        let result1 = result1.first().cloned().flatten().and_then(|m| m.accepted_id(DEFAULT_ACCEPT_THRESHOLD));
         log::debug!("匹配结果: {:?}", result1);
         log::debug!("预期结果: None");
         assert_eq!(result1, None, "破产富豪应该匹配不到任何结果");
@@ -306,32 +354,31 @@ mod tests {
                 "有一天我变成了公主".to_string(),
                 "One Day I Became a Princess".to_string(),
             ],
+            ..Default::default()
         }; // 该数据应该匹配魔法公主的小烦恼，bangumi_id: 434807
 
-        let bangumi_results2 = search_bangumi_for_works(&[anime_work2.clone()]).await?;
+        let bangumi_results2 = search_bangumi_for_works(&BangumiClient::default(), std::slice::from_ref(&anime_work2), &ai_config, &MatchSettings::default()).await?;
 
         let candidate_works2: Vec<CandidateWork> = bangumi_results2
             .iter()
             .filter_map(|result| {
-                if let Some(bangumi_id) = result.bangumi_id {
-                    Some(CandidateWork {
-                        bangumi_id,
-                        japanese_title: result.original_title.clone(),
-                        chinese_title: result.chinese_name.clone().unwrap_or_default(),
-                        air_date: result.air_date.map(|d| d.to_string()),
-                        aliases: result.aliases.clone(),
-                        score: None,
-                    })
-                } else {
-                    None
-                }
+                result.bangumi_id.map(|bangumi_id| CandidateWork {
+                    bangumi_id,
+                    japanese_title: result.original_title.clone(),
+                    chinese_title: result.chinese_name.clone().unwrap_or_default(),
+                    air_date: result.air_date.map(|d| d.to_string()),
+                    aliases: result.aliases.clone(),
+                    score: None,
+                    rank: None,
+                    info: Default::default(),
+                })
             })
             .collect();
 
         log::info!("找到 {} 个候选作品", candidate_works2.len());
         let result2 =
             batch_match_works_with_ai(&[&anime_work2], &[&candidate_works2], &ai_config).await?;
-        let result2 = result2.first().copied().flatten();
+        let result2 = result2.first().cloned().flatten().and_then(|m| m.accepted_id(DEFAULT_ACCEPT_THRESHOLD));
         log::debug!("匹配结果: {:?}", result2);
         log::debug!("预期结果: Some(434807)");
         assert_eq!(
@@ -351,32 +398,31 @@ mod tests {
                 "罗小黑战记".to_string(),
                 "The Legend of Luo Xiao Hei".to_string(),
             ],
+            ..Default::default()
         }; // 该数据应该匹配不到任何结果，但如果放大日期范围到100天会匹配到442114 （放送日本：2025-07-18）
 
-        let bangumi_results3 = search_bangumi_for_works(&[anime_work3.clone()]).await?;
+        let bangumi_results3 = search_bangumi_for_works(&BangumiClient::default(), std::slice::from_ref(&anime_work3), &ai_config, &MatchSettings::default()).await?;
 
         let candidate_works3: Vec<CandidateWork> = bangumi_results3
             .iter()
             .filter_map(|result| {
-                if let Some(bangumi_id) = result.bangumi_id {
-                    Some(CandidateWork {
-                        bangumi_id,
-                        japanese_title: result.original_title.clone(),
-                        chinese_title: result.chinese_name.clone().unwrap_or_default(),
-                        air_date: result.air_date.map(|d| d.to_string()),
-                        aliases: result.aliases.clone(),
-                        score: None,
-                    })
-                } else {
-                    None
-                }
+                result.bangumi_id.map(|bangumi_id| CandidateWork {
+                    bangumi_id,
+                    japanese_title: result.original_title.clone(),
+                    chinese_title: result.chinese_name.clone().unwrap_or_default(),
+                    air_date: result.air_date.map(|d| d.to_string()),
+                    aliases: result.aliases.clone(),
+                    score: None,
+                    rank: None,
+                    info: Default::default(),
+                })
             })
             .collect();
 
         log::info!("找到 {} 个候选作品", candidate_works3.len());
         let result3 =
             batch_match_works_with_ai(&[&anime_work3], &[&candidate_works3], &ai_config).await?;
-        let result3 = result3.first().copied().flatten();
+        let result3 = result3.first().cloned().flatten().and_then(|m| m.accepted_id(DEFAULT_ACCEPT_THRESHOLD));
         log::debug!("匹配结果: {:?}", result3);
         log::debug!("预期结果: Some(442114)");
         assert_eq!(result3, Some(442114), "罗小黑战记应该匹配到 (ID: 442114)");
@@ -397,32 +443,31 @@ mod tests {
                 "异世界四重奏 第三季".to_string(),
                 "Isekai Quartet 第三季".to_string(),
             ],
+            ..Default::default()
         }; // 该数据应该匹配到 异世界四重奏 第三季，bangumi_id: 564421
 
-        let bangumi_results4 = search_bangumi_for_works(&[anime_work4.clone()]).await?;
+        let bangumi_results4 = search_bangumi_for_works(&BangumiClient::default(), std::slice::from_ref(&anime_work4), &ai_config, &MatchSettings::default()).await?;
 
         let candidate_works4: Vec<CandidateWork> = bangumi_results4
             .iter()
             .filter_map(|result| {
-                if let Some(bangumi_id) = result.bangumi_id {
-                    Some(CandidateWork {
-                        bangumi_id,
-                        japanese_title: result.original_title.clone(),
-                        chinese_title: result.chinese_name.clone().unwrap_or_default(),
-                        air_date: result.air_date.map(|d| d.to_string()),
-                        aliases: result.aliases.clone(),
-                        score: None,
-                    })
-                } else {
-                    None
-                }
+                result.bangumi_id.map(|bangumi_id| CandidateWork {
+                    bangumi_id,
+                    japanese_title: result.original_title.clone(),
+                    chinese_title: result.chinese_name.clone().unwrap_or_default(),
+                    air_date: result.air_date.map(|d| d.to_string()),
+                    aliases: result.aliases.clone(),
+                    score: None,
+                    rank: None,
+                    info: Default::default(),
+                })
             })
             .collect();
 
         log::info!("找到 {} 个候选作品", candidate_works4.len());
         let result4 =
             batch_match_works_with_ai(&[&anime_work4], &[&candidate_works4], &ai_config).await?;
-        let result4 = result4.first().copied().flatten();
+        let result4 = result4.first().cloned().flatten().and_then(|m| m.accepted_id(DEFAULT_ACCEPT_THRESHOLD));
         log::debug!("匹配结果: {:?}", result4);
         log::debug!("预期结果: Some(564421)");
         assert_eq!(
@@ -447,32 +492,31 @@ mod tests {
                 "怪物弹珠 死亡重载".to_string(),
                 "MonSt Dead Death Reloaded".to_string(),
             ],
+            ..Default::default()
         }; // 该数据应该匹配到怪物弹珠 DEADVERSE RELOADED，bangumi_id: 570330
 
-        let bangumi_results5 = search_bangumi_for_works(&[anime_work5.clone()]).await?;
+        let bangumi_results5 = search_bangumi_for_works(&BangumiClient::default(), std::slice::from_ref(&anime_work5), &ai_config, &MatchSettings::default()).await?;
 
         let candidate_works5: Vec<CandidateWork> = bangumi_results5
             .iter()
             .filter_map(|result| {
-                if let Some(bangumi_id) = result.bangumi_id {
-                    Some(CandidateWork {
-                        bangumi_id,
-                        japanese_title: result.original_title.clone(),
-                        chinese_title: result.chinese_name.clone().unwrap_or_default(),
-                        air_date: result.air_date.map(|d| d.to_string()),
-                        aliases: result.aliases.clone(),
-                        score: None,
-                    })
-                } else {
-                    None
-                }
+                result.bangumi_id.map(|bangumi_id| CandidateWork {
+                    bangumi_id,
+                    japanese_title: result.original_title.clone(),
+                    chinese_title: result.chinese_name.clone().unwrap_or_default(),
+                    air_date: result.air_date.map(|d| d.to_string()),
+                    aliases: result.aliases.clone(),
+                    score: None,
+                    rank: None,
+                    info: Default::default(),
+                })
             })
             .collect();
 
         log::info!("找到 {} 个候选作品", candidate_works5.len());
         let result5 =
             batch_match_works_with_ai(&[&anime_work5], &[&candidate_works5], &ai_config).await?;
-        let result5 = result5.first().copied().flatten();
+        let result5 = result5.first().cloned().flatten().and_then(|m| m.accepted_id(DEFAULT_ACCEPT_THRESHOLD));
         log::debug!("匹配结果: {:?}", result5);
         log::debug!("预期结果: Some(570330)");
         assert_eq!(
@@ -506,6 +550,7 @@ mod tests {
                     "SHIBUYA HACHI 第四季".to_string(),
                     "涩谷八 第四部分".to_string(),
                 ],
+                ..Default::default()
             }, // 该数据应该匹配到SHIBUYA♡HACHI，bangumi_id: 582915
             AnimeWork {
                 original_title: "異世界食堂".to_string(),
@@ -517,6 +562,7 @@ mod tests {
                     "Isekai Shokudou".to_string(),
                     "异世界餐厅".to_string(),
                 ],
+                ..Default::default()
             }, // 该数据应该匹配到异世界食堂，bangumi_id: 192252
             // 关键边界测试案例 - 测试算法不过度匹配
             AnimeWork {
@@ -524,6 +570,7 @@ mod tests {
                 cleaned_title: "破产富豪".to_string(),
                 air_date: chrono::NaiveDate::from_ymd_opt(2025, 10, 1),
                 keywords: vec!["破产富豪".to_string(), "Bankrupt Billionaire".to_string()],
+                ..Default::default()
             }, // 该数据应该匹配不到任何结果
             AnimeWork {
                 original_title: "ある日、お姫様になってしまった件について".to_string(),
@@ -534,6 +581,7 @@ mod tests {
                     "有一天我变成了公主".to_string(),
                     "One Day I Became a Princess".to_string(),
                 ],
+                ..Default::default()
             }, // 该数据应该匹配魔法公主的小烦恼，bangumi_id: 434807
             AnimeWork {
                 original_title: "架空のアニメ作品".to_string(),
@@ -544,6 +592,7 @@ mod tests {
                     "Fictional Anime Work".to_string(),
                     "不存在于Bangumi的作品".to_string(),
                 ],
+                ..Default::default()
             }, // 该数据应该匹配不到任何结果
             AnimeWork {
                 original_title: "羅小黒戦記".to_string(),
@@ -553,6 +602,7 @@ mod tests {
                     "罗小黑战记".to_string(),
                     "The Legend of Luo Xiao Hei".to_string(),
                 ],
+                ..Default::default()
             }, // 该数据应该匹配不到任何结果，但如果放大日期范围到100天的话会匹配到442114 （放送日本：2025-07-18）
             AnimeWork {
                 original_title: "異世界かるてっと3".to_string(),
@@ -567,6 +617,7 @@ mod tests {
                     "异世界四重奏 第三季".to_string(),
                     "Isekai Quartet 第三季".to_string(),
                 ],
+                ..Default::default()
             }, // 该数据应该匹配到 异世界四重奏 第三季，bangumi_id: 564421
             AnimeWork {
                 original_title: "モンスターストライク デッドバースリローデッド".to_string(),
@@ -581,6 +632,7 @@ mod tests {
                     "怪物弹珠 死亡重载".to_string(),
                     "MonSt Dead Death Reloaded".to_string(),
                 ],
+                ..Default::default()
             }, // 该数据应该匹配到怪物弹珠 DEADVERSE RELOADED，bangumi_id: 570330
             AnimeWork {
                 original_title: "ポケモンコンシェルジュ【2nd Season】".to_string(),
@@ -594,6 +646,7 @@ mod tests {
                     "宝可梦礼宾部2".to_string(),
                     "Pokemon Concierge S2".to_string(),
                 ],
+                ..Default::default()
             }, // 该数据应该匹配到宝可梦 礼宾部 新剧集，bangumi_id: 481530
             // 新增测试案例 - 2025年10月新番
             AnimeWork {
@@ -608,6 +661,7 @@ mod tests {
                     "Ganglion 2025".to_string(),
                     "Ganglion new anime".to_string(),
                 ],
+                ..Default::default()
             }, // 该数据应该匹配到ガングリオン，bangumi_id: 581598
             AnimeWork {
                 original_title: "SANDA【サンダ】".to_string(),
@@ -621,6 +675,7 @@ mod tests {
                     "SANDA 2025".to_string(),
                     "Sanda new series".to_string(),
                 ],
+                ..Default::default()
             }, // 该数据应该匹配到SANDA，bangumi_id: 503303
             AnimeWork {
                 original_title: "信じていた仲間達にダンジョン奥地で殺されかけたがギフト『無限ガチャ』でレベル9999の仲間達を手に入れて元パーティーメンバーと世界に復讐＆『ざまぁ！』します！".to_string(),
@@ -634,6 +689,7 @@ mod tests {
                     "Mugen Gacha revenge anime".to_string(),
                     "Level 9999 companions revenge".to_string(),
                 ],
+                ..Default::default()
             }, // 该数据应该匹配到信じていた仲間達にダンジョン奥地で殺されかけたがギフト『無限ガチャ』でレベル9999の仲間達を手に入れて元パーティーメンバーと世界に復讐＆『ざまぁ！』します！，bangumi_id: 524195
             AnimeWork {
                 original_title: "最後にひとつだけお願いしてもよろしいでしょうか".to_string(),
@@ -647,6 +703,7 @@ mod tests {
                     "最后一个请求".to_string(),
                     "One Last Request anime".to_string(),
                 ],
+                ..Default::default()
             }, // 该数据应该匹配到最后にひとつだけお願いしてもよろしいでしょうか，bangumi_id: 513348
         ];
 
@@ -660,7 +717,7 @@ mod tests {
 
         // 使用search_bangumi_for_works获取所有Bangumi匹配结果
         log::info!("🚀 执行Bangumi搜索和AI匹配...");
-        let bangumi_results = search_bangumi_for_works(&source_works).await?;
+        let bangumi_results = search_bangumi_for_works(&BangumiClient::default(), &source_works, &AiConfig::deepseek(), &MatchSettings::default()).await?;
 
         // 直接从Bangumi结果中提取匹配的Bangumi ID
         let batch_results: Vec<Option<u32>> = bangumi_results
@@ -734,14 +791,14 @@ mod tests {
 
     #[tokio::test]
     async fn test_problematic_searches() -> Result<(), Box<dyn std::error::Error>> {
-        use crate::meta_providers::bangumi::search_bangumi_with_keyword;
-        let client = reqwest::Client::new();
+        let client = BangumiClient::default();
+        let window = AirDateWindow::from_days(MatchSettings::default().air_date_window_days);
         let isekai_date_range = chrono::NaiveDate::from_ymd_opt(2025, 10, 13);
         let monster_date_range = chrono::NaiveDate::from_ymd_opt(2025, 10, 21);
 
         log::info!("🔍 测试搜索: 異世界かるてっと3");
         let subjects =
-            search_bangumi_with_keyword(&client, "異世界かるてっと3", &isekai_date_range).await?;
+            search_bangumi_with_window(&client, "異世界かるてっと3", &isekai_date_range, window).await?;
         log::info!("搜索结果数量: {}", subjects.len());
         for subject in &subjects {
             log::info!("  作品: {} (ID: {})", subject.name, subject.id);
@@ -749,7 +806,7 @@ mod tests {
 
         log::info!("🔍 测试搜索: 異世界かるてっと");
         let subjects2 =
-            search_bangumi_with_keyword(&client, "異世界かるてっと", &isekai_date_range).await?;
+            search_bangumi_with_window(&client, "異世界かるてっと", &isekai_date_range, window).await?;
         log::info!("搜索结果数量: {}", subjects2.len());
         for subject in &subjects2 {
             log::info!("  作品: {} (ID: {})", subject.name, subject.id);
@@ -757,17 +814,18 @@ mod tests {
 
         log::info!("🔍 测试搜索: 异世界四重奏");
         let subjects3 =
-            search_bangumi_with_keyword(&client, "异世界四重奏", &isekai_date_range).await?;
+            search_bangumi_with_window(&client, "异世界四重奏", &isekai_date_range, window).await?;
         log::info!("搜索结果数量: {}", subjects3.len());
         for subject in &subjects3 {
             log::info!("  作品: {} (ID: {})", subject.name, subject.id);
         }
 
         log::info!("🔍 测试搜索: モンスターストライク デッドバースリローデッド");
-        let subjects4 = search_bangumi_with_keyword(
+        let subjects4 = search_bangumi_with_window(
             &client,
             "モンスターストライク デッドバースリローデッド",
             &monster_date_range,
+            window,
         )
         .await?;
         log::info!("搜索结果数量: {}", subjects4.len());
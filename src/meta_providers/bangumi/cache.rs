@@ -0,0 +1,243 @@
+use crate::models::BangumiSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 缓存条目：原始JSON响应及获取时间
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    /// 获取时间（Unix时间戳，秒）
+    fetched_at: i64,
+    /// 搜索缓存对应的关键字或条目ID，按此删除缓存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    data: serde_json::Value,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheData {
+    #[serde(default)]
+    searches: HashMap<String, CacheEntry>,
+    #[serde(default)]
+    subjects: HashMap<u32, CacheEntry>,
+}
+
+/// Bangumi搜索结果和条目详情的磁盘缓存
+///
+/// 读取时跳过超过有效期的条目，保存时一并清除。
+#[derive(Debug)]
+pub struct BangumiCache {
+    path: PathBuf,
+    ttl_seconds: i64,
+    data: Mutex<CacheData>,
+    dirty: AtomicBool,
+}
+
+impl BangumiCache {
+    /// 缓存文件的默认位置
+    pub fn default_path() -> PathBuf {
+        crate::utils::cache_dir().join("bangumi_cache.json")
+    }
+
+    /// 按配置打开缓存，有效期为0时不使用缓存
+    pub fn from_settings(settings: &BangumiSettings) -> Option<Self> {
+        if settings.cache_ttl_hours == 0 {
+            return None;
+        }
+        let path = settings
+            .cache_file
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(Self::default_path);
+        Some(Self::open(&path, settings.cache_ttl_hours))
+    }
+
+    /// 读取缓存文件，文件不存在或损坏时从空缓存开始
+    pub fn open(path: &Path, ttl_hours: u64) -> Self {
+        let data = match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("Bangumi缓存文件 {} 格式错误，将重新建立: {}", path.display(), e);
+                CacheData::default()
+            }),
+            Err(_) => CacheData::default(),
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            ttl_seconds: (ttl_hours * 3600) as i64,
+            data: Mutex::new(data),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    fn is_fresh(&self, entry: &CacheEntry) -> bool {
+        chrono::Utc::now().timestamp() - entry.fetched_at < self.ttl_seconds
+    }
+
+    fn new_entry(target: Option<String>, data: serde_json::Value) -> CacheEntry {
+        CacheEntry {
+            fetched_at: chrono::Utc::now().timestamp(),
+            target,
+            data,
+        }
+    }
+
+    fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+
+    pub fn get_search(&self, key: &str) -> Option<serde_json::Value> {
+        let data = self.data.lock().unwrap();
+        data.searches
+            .get(key)
+            .filter(|entry| self.is_fresh(entry))
+            .map(|entry| entry.data.clone())
+    }
+
+    /// 写入搜索缓存，`target` 为搜索的关键字或条目ID
+    pub fn put_search(&self, key: &str, target: &str, value: serde_json::Value) {
+        self.data
+            .lock()
+            .unwrap()
+            .searches
+            .insert(key.to_string(), Self::new_entry(Some(target.to_string()), value));
+        self.mark_dirty();
+    }
+
+    pub fn get_subject(&self, subject_id: u32) -> Option<serde_json::Value> {
+        let data = self.data.lock().unwrap();
+        data.subjects
+            .get(&subject_id)
+            .filter(|entry| self.is_fresh(entry))
+            .map(|entry| entry.data.clone())
+    }
+
+    pub fn put_subject(&self, subject_id: u32, value: serde_json::Value) {
+        self.data
+            .lock()
+            .unwrap()
+            .subjects
+            .insert(subject_id, Self::new_entry(None, value));
+        self.mark_dirty();
+    }
+
+    /// 清空全部缓存
+    pub fn clear(&self) {
+        let mut data = self.data.lock().unwrap();
+        data.searches.clear();
+        data.subjects.clear();
+        self.mark_dirty();
+    }
+
+    /// 删除关键字或条目ID与 `target` 完全相同的搜索缓存，以及该ID的条目缓存，返回删除的条目数
+    pub fn invalidate(&self, target: &str) -> usize {
+        let target = target.trim();
+        let mut data = self.data.lock().unwrap();
+        let before = data.searches.len() + data.subjects.len();
+        data.searches.retain(|_, entry| entry.target.as_deref() != Some(target));
+        if let Ok(subject_id) = target.parse::<u32>() {
+            data.subjects.remove(&subject_id);
+        }
+        let removed = before - data.searches.len() - data.subjects.len();
+        if removed > 0 {
+            self.mark_dirty();
+        }
+        removed
+    }
+
+    /// 有改动时写回缓存文件，同时清除过期条目
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut data = self.data.lock().unwrap();
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        data.searches.retain(|_, entry| now - entry.fetched_at < self.ttl_seconds);
+        data.subjects.retain(|_, entry| now - entry.fetched_at < self.ttl_seconds);
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string(&*data)?)?;
+        log::debug!("Bangumi缓存已保存到: {}", self.path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::meta_providers::bangumi::BangumiClient;
+    use crate::test_support::spawn_mock_server;
+
+    #[tokio::test]
+    async fn test_bangumi_cache() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::meta_providers::bangumi::cache::BangumiCache;
+        use crate::models::BangumiSettings;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let request_count = Arc::new(AtomicUsize::new(0));
+        let counter = request_count.clone();
+        let base_url = spawn_mock_server(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if request.starts_with("GET /v0/subjects/7 ") {
+                (200, r#"{"id": 7, "name": "作品", "name_cn": "作品中文", "eps": 12}"#.to_string())
+            } else {
+                let data = serde_json::json!([{"id": 7, "name": "作品", "name_cn": "作品中文", "date": "2025-10-01"}]);
+                (200, serde_json::json!({"total": 1, "limit": 20, "offset": 0, "data": data}).to_string())
+            }
+        })
+        .await;
+
+        let cache_file = std::env::temp_dir().join(format!("brb_cache_test_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&cache_file);
+        let settings = BangumiSettings {
+            base_url,
+            cache_ttl_hours: 1,
+            cache_file: Some(cache_file.to_string_lossy().into_owned()),
+            ..BangumiSettings::default()
+        };
+
+        // 第一次请求后写入缓存文件
+        let body = serde_json::json!({"keyword": "作品"});
+        let client = BangumiClient::new(&settings)?;
+        assert_eq!(client.search_subjects(&body).await?.len(), 1);
+        assert_eq!(client.get_subject(7).await?.eps, 12);
+        client.save_cache();
+        assert_eq!(request_count.load(Ordering::SeqCst), 2);
+
+        // 新客户端从缓存文件读取，不再发送请求
+        let client = BangumiClient::new(&settings)?;
+        assert_eq!(client.search_subjects(&body).await?[0].id, 7);
+        assert_eq!(client.get_subject(7).await?.name_cn, "作品中文");
+        assert_eq!(request_count.load(Ordering::SeqCst), 2);
+
+        // 按关键字和条目ID删除缓存，只删除完全相同的关键字
+        let cache = BangumiCache::open(&cache_file, 1);
+        assert_eq!(cache.invalidate("作"), 0);
+        assert_eq!(cache.invalidate("作品"), 1);
+        assert_eq!(cache.invalidate("7"), 1);
+        cache.save()?;
+        let client = BangumiClient::new(&settings)?;
+        client.search_subjects(&body).await?;
+        client.get_subject(7).await?;
+        client.save_cache();
+        assert_eq!(request_count.load(Ordering::SeqCst), 4);
+
+        // 清空缓存，有效期为0时不使用缓存
+        let cache = BangumiCache::open(&cache_file, 1);
+        cache.clear();
+        cache.save()?;
+        assert!(BangumiCache::open(&cache_file, 1).get_subject(7).is_none());
+        let client = BangumiClient::new(&BangumiSettings { cache_ttl_hours: 0, ..settings })?;
+        client.get_subject(7).await?;
+        client.get_subject(7).await?;
+        assert_eq!(request_count.load(Ordering::SeqCst), 6);
+
+        let _ = std::fs::remove_file(&cache_file);
+        Ok(())
+    }
+}
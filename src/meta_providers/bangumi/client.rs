@@ -0,0 +1,475 @@
+use super::cache::BangumiCache;
+use crate::models::{
+    BangumiCollection, BangumiEpisode, BangumiPage, BangumiRelatedSubject, BangumiSettings, BangumiSubject, BangumiSubjectDetail,
+    SearchFilter,
+};
+use crate::utils::RateLimiter;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 读取剧集列表时每页的条数
+const EPISODE_PAGE_LIMIT: usize = 100;
+
+/// 分页读取时最多请求的页数，避免API返回异常的 `total` 时不停翻页
+const MAX_PAGES: usize = 100;
+
+/// 第一次重试前的等待时间，之后每次加倍
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Bangumi API客户端
+///
+/// 所有Bangumi请求共用一个 `reqwest::Client`，API地址可配置为本地代理或测试服务。
+/// 配置了访问令牌时，每个请求都带上 `Authorization: Bearer` 头；
+/// 搜索结果和条目详情会写入共享的磁盘缓存。
+/// 克隆出的客户端共享同一个速率限制器，遇到429或5xx响应时按指数退避重试。
+#[derive(Debug, Clone)]
+pub struct BangumiClient {
+    http: reqwest::Client,
+    base_url: String,
+    page_limit: usize,
+    max_results: usize,
+    authenticated: bool,
+    cache: Option<Arc<BangumiCache>>,
+    /// 同时进行的搜索请求数上限
+    pub concurrency: usize,
+    rate_limiter: Arc<RateLimiter>,
+    max_retries: u32,
+    /// 搜索候选作品时的过滤条件
+    pub search_filter: SearchFilter,
+}
+
+impl Default for BangumiClient {
+    fn default() -> Self {
+        Self::new(&BangumiSettings::default()).expect("默认Bangumi客户端配置无效")
+    }
+}
+
+impl BangumiClient {
+    pub fn new(settings: &BangumiSettings) -> Result<Self, Box<dyn std::error::Error>> {
+        let access_token = settings.access_token();
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(token) = &access_token {
+            let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| "Bangumi访问令牌包含无效字符")?;
+            value.set_sensitive(true);
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
+
+        let http = reqwest::Client::builder()
+            .user_agent(&settings.user_agent)
+            .default_headers(headers)
+            .build()?;
+
+        Ok(Self {
+            http,
+            base_url: settings.base_url.trim_end_matches('/').to_string(),
+            page_limit: settings.page_limit.max(1),
+            max_results: settings.max_results.max(1),
+            authenticated: access_token.is_some(),
+            cache: BangumiCache::from_settings(settings).map(Arc::new),
+            concurrency: settings.concurrency.max(1),
+            rate_limiter: Arc::new(RateLimiter::new(settings.request_interval())),
+            max_retries: settings.max_retries,
+            search_filter: settings.search_filter.clone(),
+        })
+    }
+
+    /// 是否配置了访问令牌
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated
+    }
+
+    /// 检查访问令牌，返回令牌所属的用户名
+    ///
+    /// 令牌无效或已过期时返回明确的错误。
+    pub async fn verify_token(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let me: serde_json::Value = self.get_json("/v0/me").await?;
+        Ok(me["username"].as_str().unwrap_or_default().to_string())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// 发送GET请求并解析JSON
+    pub async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, Box<dyn std::error::Error>> {
+        let response = self.send(self.http.get(self.url(path))).await?;
+        self.parse_response(path, response).await
+    }
+
+    /// 获取条目详情，优先使用缓存
+    pub async fn get_subject(&self, subject_id: u32) -> Result<BangumiSubjectDetail, Box<dyn std::error::Error>> {
+        if let Some(cached) = self.cache.as_ref().and_then(|cache| cache.get_subject(subject_id)) {
+            return Ok(serde_json::from_value(cached)?);
+        }
+
+        let detail: serde_json::Value = self.get_json(&format!("/v0/subjects/{}", subject_id)).await?;
+        if let Some(cache) = &self.cache {
+            cache.put_subject(subject_id, detail.clone());
+        }
+        Ok(serde_json::from_value(detail)?)
+    }
+
+    /// 获取条目的本篇剧集列表，优先使用缓存
+    pub async fn get_episodes(&self, subject_id: u32) -> Result<Vec<BangumiEpisode>, Box<dyn std::error::Error>> {
+        let cache_key = format!("{} episodes {}", self.base_url, subject_id);
+        let cached = self.cache.as_ref().and_then(|cache| cache.get_search(&cache_key));
+        let raw_episodes = match cached {
+            Some(serde_json::Value::Array(raw_episodes)) => raw_episodes,
+            _ => {
+                let raw_episodes = self.fetch_episode_pages(subject_id).await?;
+                if let Some(cache) = &self.cache {
+                    cache.put_search(&cache_key, &subject_id.to_string(), serde_json::Value::Array(raw_episodes.clone()));
+                }
+                raw_episodes
+            }
+        };
+
+        Ok(raw_episodes
+            .into_iter()
+            .filter_map(|episode| serde_json::from_value::<BangumiEpisode>(episode).ok())
+            .collect())
+    }
+
+    /// 获取条目的关联条目，优先使用缓存
+    pub async fn get_related_subjects(
+        &self,
+        subject_id: u32,
+    ) -> Result<Vec<BangumiRelatedSubject>, Box<dyn std::error::Error>> {
+        let cache_key = format!("{} relations {}", self.base_url, subject_id);
+        let related = match self.cache.as_ref().and_then(|cache| cache.get_search(&cache_key)) {
+            Some(related) => related,
+            None => {
+                let related: serde_json::Value = self.get_json(&format!("/v0/subjects/{}/subjects", subject_id)).await?;
+                if let Some(cache) = &self.cache {
+                    cache.put_search(&cache_key, &subject_id.to_string(), related.clone());
+                }
+                related
+            }
+        };
+        Ok(serde_json::from_value(related)?)
+    }
+
+    /// 读取用户的动画收藏，`collection_type` 为空时读取全部状态
+    ///
+    /// 收藏经常变化，不使用缓存。
+    pub async fn get_user_collections(
+        &self,
+        username: &str,
+        collection_type: Option<u8>,
+    ) -> Result<Vec<BangumiCollection>, Box<dyn std::error::Error>> {
+        let path = format!("/v0/users/{}/collections", username);
+        let mut collections = Vec::new();
+        let mut offset = 0;
+
+        for _ in 0..MAX_PAGES {
+            let mut query = vec![
+                ("subject_type", 2),
+                ("limit", self.page_limit),
+                ("offset", offset),
+            ];
+            if let Some(collection_type) = collection_type {
+                query.push(("type", collection_type as usize));
+            }
+            let response = self.send(self.http.get(self.url(&path)).query(&query)).await?;
+            let page: BangumiPage<BangumiCollection> = self.parse_response(&path, response).await?;
+            let count = page.data.len();
+            collections.extend(page.data);
+
+            offset = page.offset + count;
+            if count == 0 || count < page.limit || offset >= page.total {
+                break;
+            }
+        }
+
+        Ok(collections)
+    }
+
+    /// 设置当前用户对条目的收藏状态，未收藏时新建收藏，需要访问令牌
+    pub async fn update_collection(&self, subject_id: u32, collection_type: u8) -> Result<(), Box<dyn std::error::Error>> {
+        let path = format!("/v0/users/-/collections/{}", subject_id);
+        let request = self
+            .http
+            .post(self.url(&path))
+            .json(&serde_json::json!({ "type": collection_type }));
+        let response = self.send(request).await?;
+        self.check_response(&path, response).await?;
+        Ok(())
+    }
+
+    async fn fetch_episode_pages(&self, subject_id: u32) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
+        let path = "/v0/episodes";
+        let mut episodes = Vec::new();
+        let mut offset = 0;
+
+        for _ in 0..MAX_PAGES {
+            let request = self.http.get(self.url(path)).query(&[
+                ("subject_id", subject_id as usize),
+                ("type", 0),
+                ("limit", EPISODE_PAGE_LIMIT),
+                ("offset", offset),
+            ]);
+            let response = self.send(request).await?;
+            let page: BangumiPage<serde_json::Value> = self.parse_response(path, response).await?;
+            let count = page.data.len();
+            episodes.extend(page.data);
+
+            offset = page.offset + count;
+            if count == 0 || count < page.limit || offset >= page.total {
+                break;
+            }
+        }
+
+        Ok(episodes)
+    }
+
+    /// 把缓存写回磁盘，失败时只记录警告
+    pub fn save_cache(&self) {
+        if let Some(cache) = &self.cache
+            && let Err(e) = cache.save()
+        {
+            log::warn!("保存Bangumi缓存失败: {}", e);
+        }
+    }
+
+    /// 发送带分页参数的POST请求并解析JSON
+    async fn post_page<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        offset: usize,
+        body: &serde_json::Value,
+    ) -> Result<BangumiPage<T>, Box<dyn std::error::Error>> {
+        let request = self
+            .http
+            .post(self.url(path))
+            .query(&[("limit", self.page_limit), ("offset", offset)])
+            .json(body);
+        let response = self.send(request).await?;
+        self.parse_response(path, response).await
+    }
+
+    /// 按速率限制发送请求，429、5xx响应和超时按指数退避重试
+    ///
+    /// 响应带有 `Retry-After` 头时按其指定的秒数等待。重试次数用完后返回最后一次的响应。
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+        let mut attempt = 0;
+        loop {
+            self.rate_limiter.acquire().await;
+            let attempt_request = request.try_clone().ok_or("Bangumi请求无法重试")?;
+            let backoff = RETRY_BASE_DELAY * 2u32.pow(attempt);
+
+            let delay = match attempt_request.send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    let retryable = status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+                    if !retryable || attempt >= self.max_retries {
+                        return Ok(response);
+                    }
+                    let retry_after = response
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.trim().parse::<u64>().ok())
+                        .map(Duration::from_secs);
+                    log::debug!("Bangumi API返回 {}，准备第 {} 次重试", status, attempt + 1);
+                    retry_after.unwrap_or(backoff)
+                }
+                Err(e) if e.is_timeout() && attempt < self.max_retries => {
+                    log::debug!("Bangumi API请求超时，准备第 {} 次重试", attempt + 1);
+                    backoff
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn parse_response<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        response: reqwest::Response,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        Ok(self.check_response(path, response).await?.json().await?)
+    }
+
+    /// 检查响应状态，失败时返回带有错误说明的错误
+    async fn check_response(
+        &self,
+        path: &str,
+        response: reqwest::Response,
+    ) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED && self.authenticated {
+            return Err("Bangumi访问令牌无效或已过期，请检查配置中的 access_token 或环境变量 BANGUMI_ACCESS_TOKEN".into());
+        }
+        if !status.is_success() {
+            // Bangumi的错误响应带有description字段
+            let description = response
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|body| body["description"].as_str().map(|d| d.to_string()))
+                .unwrap_or_default();
+            return Err(format!("Bangumi API请求 {} 失败，状态码: {} {}", path, status, description).trim_end().into());
+        }
+        Ok(response)
+    }
+
+    /// 搜索条目，按 `total`/`offset` 逐页读取，最多读取 `max_results` 条
+    ///
+    /// 无法解析的条目会被跳过；相同的搜索在缓存有效期内直接使用缓存。
+    pub async fn search_subjects(
+        &self,
+        body: &serde_json::Value,
+    ) -> Result<Vec<BangumiSubject>, Box<dyn std::error::Error>> {
+        let cache_key = format!("{} {} {}", self.base_url, self.max_results, body);
+        let cached = self.cache.as_ref().and_then(|cache| cache.get_search(&cache_key));
+        let raw_subjects = match cached {
+            Some(serde_json::Value::Array(raw_subjects)) => raw_subjects,
+            _ => {
+                let raw_subjects = self.fetch_search_pages(body).await?;
+                if let Some(cache) = &self.cache {
+                    let keyword = body["keyword"].as_str().unwrap_or_default();
+                    cache.put_search(&cache_key, keyword, serde_json::Value::Array(raw_subjects.clone()));
+                }
+                raw_subjects
+            }
+        };
+
+        Ok(raw_subjects
+            .into_iter()
+            .filter_map(|subject_data| serde_json::from_value::<BangumiSubject>(subject_data).ok())
+            .collect())
+    }
+
+    async fn fetch_search_pages(
+        &self,
+        body: &serde_json::Value,
+    ) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
+        let mut subjects = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let page: BangumiPage<serde_json::Value> =
+                self.post_page("/v0/search/subjects", offset, body).await?;
+            let count = page.data.len();
+            subjects.extend(page.data);
+
+            offset = page.offset + count;
+            if count == 0 || count < page.limit || offset >= page.total || offset >= self.max_results {
+                break;
+            }
+        }
+
+        subjects.truncate(self.max_results);
+        Ok(subjects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::meta_providers::bangumi::BangumiClient;
+    use crate::test_support::spawn_mock_server;
+
+    #[tokio::test]
+    async fn test_bangumi_client_pagination() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::models::BangumiSettings;
+        use std::sync::{Arc, Mutex};
+
+        let requests = Arc::new(Mutex::new(Vec::<String>::new()));
+        let recorded = requests.clone();
+        let base_url = spawn_mock_server(move |request| {
+            recorded.lock().unwrap().push(request.to_ascii_lowercase());
+            let offset = if request.contains("offset=2") { 2 } else { 0 };
+            let data: Vec<serde_json::Value> = (offset..(offset + 2).min(3))
+                .map(|i| serde_json::json!({"id": 100 + i, "name": format!("作品{}", i), "name_cn": "", "rating": {"rank": 10}}))
+                .collect();
+            (200, serde_json::json!({"total": 3, "limit": 2, "offset": offset, "data": data}).to_string())
+        })
+        .await;
+
+        let settings = BangumiSettings {
+            base_url: format!("{}/", base_url),
+            user_agent: "brb-test/1.0".to_string(),
+            page_limit: 2,
+            max_results: 10,
+            access_token: None,
+            cache_ttl_hours: 0,
+            cache_file: None,
+            concurrency: 1,
+            requests_per_second: 0.0,
+            max_retries: 0,
+            search_filter: crate::models::SearchFilter::default(),
+        };
+        let client = BangumiClient::new(&settings)?;
+        let subjects = client.search_subjects(&serde_json::json!({"keyword": "作品"})).await?;
+        assert_eq!(subjects.iter().map(|s| s.id).collect::<Vec<_>>(), vec![100, 101, 102]);
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|r| r.starts_with("post /v0/search/subjects?limit=2")));
+        assert!(requests.iter().all(|r| r.contains("user-agent: brb-test/1.0")));
+
+        // 达到最大条数后不再翻页
+        let client = BangumiClient::new(&BangumiSettings { max_results: 2, ..settings.clone() })?;
+        let subjects = client.search_subjects(&serde_json::json!({"keyword": "作品"})).await?;
+        assert_eq!(subjects.len(), 2);
+
+        // 收藏列表返回不满一页时不再翻页，即使total更大
+        let collection_requests = Arc::new(Mutex::new(0));
+        let counter = collection_requests.clone();
+        let base_url = spawn_mock_server(move |_| {
+            *counter.lock().unwrap() += 1;
+            let data = serde_json::json!([{"subject_id": 1, "type": 3}]);
+            (200, serde_json::json!({"total": 1000, "limit": 2, "offset": 0, "data": data}).to_string())
+        })
+        .await;
+        let client = BangumiClient::new(&BangumiSettings { base_url, ..settings })?;
+        assert_eq!(client.get_user_collections("tester", None).await?.len(), 1);
+        assert_eq!(*collection_requests.lock().unwrap(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bangumi_access_token() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::models::BangumiSettings;
+
+        let base_url = spawn_mock_server(|request| {
+            let authorized = request
+                .lines()
+                .any(|line| line.eq_ignore_ascii_case("authorization: Bearer valid-token"));
+            match (authorized, request.starts_with("GET /v0/me ")) {
+                (true, true) => (200, r#"{"username": "tester"}"#.to_string()),
+                (true, false) => (200, r#"{"total": 0, "limit": 20, "offset": 0, "data": []}"#.to_string()),
+                (false, _) => (401, r#"{"title": "Unauthorized", "description": "access token has expired"}"#.to_string()),
+            }
+        })
+        .await;
+        let settings = |token: &str| BangumiSettings {
+            base_url: base_url.clone(),
+            access_token: Some(token.to_string()),
+            cache_ttl_hours: 0,
+            ..BangumiSettings::default()
+        };
+
+        // 令牌随每个请求发送
+        let client = BangumiClient::new(&settings("valid-token"))?;
+        assert!(client.is_authenticated());
+        assert_eq!(client.verify_token().await?, "tester");
+        assert!(client.search_subjects(&serde_json::json!({"keyword": "作品"})).await?.is_empty());
+
+        // 无效令牌给出明确提示
+        let client = BangumiClient::new(&settings("expired-token"))?;
+        let error = client.verify_token().await.unwrap_err().to_string();
+        assert!(error.contains("令牌无效或已过期"), "{}", error);
+
+        // 空令牌视为未配置
+        assert!(!BangumiClient::new(&settings("  "))?.is_authenticated());
+        Ok(())
+    }
+}
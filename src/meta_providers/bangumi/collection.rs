@@ -0,0 +1,290 @@
+use super::infobox::parse_infobox_date;
+use super::{BangumiClient, fetch_result_details};
+use crate::models::{
+    BangumiCollection, BangumiResult, CleaningMethod, CollectionStatus, CollectionUpdateSettings, InfoboxInfo,
+};
+use std::collections::HashSet;
+
+/// 收藏更新的结果
+#[derive(Debug, Default)]
+pub struct CollectionUpdateReport {
+    /// 已更新（试运行时为将要更新）的条目ID
+    pub updated: Vec<u32>,
+    /// 已在收藏中而跳过的条目ID
+    pub skipped: Vec<u32>,
+    /// 更新失败的条目ID和原因
+    pub failed: Vec<(u32, String)>,
+}
+
+/// 读取用户收藏的动画，转换为匹配结果并获取条目详情
+///
+/// 多个收藏状态中的同一条目只保留一次。
+pub async fn collection_results(
+    client: &BangumiClient,
+    username: &str,
+    statuses: &[CollectionStatus],
+) -> Result<Vec<BangumiResult>, Box<dyn std::error::Error>> {
+    let mut collections: Vec<BangumiCollection> = Vec::new();
+    for status in statuses {
+        for collection in client.get_user_collections(username, Some(status.api_type())).await? {
+            if !collections.iter().any(|c| c.subject_id == collection.subject_id) {
+                collections.push(collection);
+            }
+        }
+    }
+    log::info!("从 {} 的收藏中读取到 {} 部动画", username, collections.len());
+
+    let mut results: Vec<BangumiResult> = collections.iter().map(result_from_collection).collect();
+    fetch_result_details(client, &mut results).await;
+    client.save_cache();
+
+    // 收藏中没有条目信息时使用详情中的名称
+    for result in results.iter_mut() {
+        if result.cleaned_title.is_empty()
+            && let Some(detail) = &result.details
+        {
+            result.original_title = detail.name.clone();
+            result.cleaned_title = detail.name.clone();
+        }
+    }
+
+    Ok(results)
+}
+
+/// 收藏的条目直接作为确定的匹配结果
+pub fn result_from_collection(collection: &BangumiCollection) -> BangumiResult {
+    let subject = collection.subject.as_ref();
+    let name = subject.map(|s| s.name.clone()).unwrap_or_default();
+
+    BangumiResult {
+        original_title: name.clone(),
+        cleaned_title: name,
+        bangumi_id: Some(collection.subject_id),
+        chinese_name: subject.map(|s| s.name_cn.clone()).filter(|name_cn| !name_cn.is_empty()),
+        aliases: Vec::new(),
+        air_date: subject
+            .and_then(|s| s.date.as_deref())
+            .and_then(parse_infobox_date),
+        keywords: Vec::new(),
+        match_confidence: Some(1.0),
+        match_reasoning: Some(match CollectionStatus::from_api_type(collection.collection_type) {
+            Some(status) => format!("来自Bangumi收藏（{}）", status.label()),
+            None => "来自Bangumi收藏".to_string(),
+        }),
+        candidate_count: 0,
+        review_notes: Vec::new(),
+        cleaned_by: CleaningMethod::Raw,
+        details: None,
+        episode_range: None,
+        prequels: Vec::new(),
+        air_date_window: None,
+        info: InfoboxInfo::default(),
+    }
+}
+
+/// 把已匹配的作品加入当前用户的收藏，已收藏的条目保持不变
+///
+/// 试运行时只列出将要更新的作品。
+pub async fn update_collections(
+    client: &BangumiClient,
+    results: &[BangumiResult],
+    settings: &CollectionUpdateSettings,
+) -> Result<CollectionUpdateReport, Box<dyn std::error::Error>> {
+    if !client.is_authenticated() {
+        return Err("更新Bangumi收藏需要配置访问令牌".into());
+    }
+    let username = client.verify_token().await?;
+    let collected: HashSet<u32> = client
+        .get_user_collections(&username, None)
+        .await?
+        .iter()
+        .map(|collection| collection.subject_id)
+        .collect();
+
+    let mut report = CollectionUpdateReport::default();
+    let mut seen = HashSet::new();
+    for result in results {
+        let Some(bangumi_id) = result.bangumi_id else {
+            continue;
+        };
+        if !seen.insert(bangumi_id) {
+            continue;
+        }
+        let name = result.chinese_name.as_deref().unwrap_or(&result.cleaned_title);
+
+        if collected.contains(&bangumi_id) {
+            log::debug!("{} ({}) 已在收藏中，跳过", name, bangumi_id);
+            report.skipped.push(bangumi_id);
+            continue;
+        }
+        if settings.dry_run {
+            log::info!("[试运行] 将标记为{}: {} ({})", settings.status.label(), name, bangumi_id);
+            report.updated.push(bangumi_id);
+            continue;
+        }
+
+        match client.update_collection(bangumi_id, settings.status.api_type()).await {
+            Ok(()) => {
+                log::info!("已标记为{}: {} ({})", settings.status.label(), name, bangumi_id);
+                report.updated.push(bangumi_id);
+            }
+            Err(e) => {
+                log::warn!("更新 {} ({}) 的收藏失败: {}", name, bangumi_id, e);
+                report.failed.push((bangumi_id, e.to_string()));
+            }
+        }
+    }
+
+    log::info!(
+        "收藏更新{}: {} 个{}，{} 个已收藏跳过，{} 个失败",
+        if settings.dry_run { "（试运行）" } else { "" },
+        report.updated.len(),
+        if settings.dry_run { "待更新" } else { "已更新" },
+        report.skipped.len(),
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::meta_providers::bangumi::BangumiClient;
+    use crate::test_support::spawn_mock_server;
+
+    #[tokio::test]
+    async fn test_bangumi_collection_source() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::meta_providers::bangumi::collection::collection_results;
+        use crate::models::{BangumiSettings, CollectionStatus, Task};
+        use crate::rules::q_bittorrent::generate_qb_rules;
+
+        // 在看：10、11（每页1条）；想看：11、12
+        let base_url = spawn_mock_server(|request| {
+            let first_line = request.lines().next().unwrap_or_default();
+            if first_line.starts_with("GET /v0/users/tester/collections?subject_type=2") {
+                let watching = first_line.contains("&type=3");
+                let offset = if first_line.contains("offset=1") { 1 } else { 0 };
+                let items = if watching { vec![[10, 11][offset]] } else { vec![11, 12] };
+                let collection_type = if watching { 3 } else { 1 };
+                let data: Vec<_> = items
+                    .iter()
+                    .map(|id| serde_json::json!({
+                        "subject_id": id,
+                        "subject_type": 2,
+                        "type": collection_type,
+                        "subject": {"id": id, "name": format!("作品{}", id), "name_cn": format!("中文{}", id), "date": "2025-10-03"}
+                    }))
+                    .collect();
+                return (200, serde_json::json!({"total": 2, "limit": 1, "offset": offset, "data": data}).to_string());
+            }
+            let subject_id = first_line
+                .strip_prefix("GET /v0/subjects/")
+                .and_then(|rest| rest.strip_suffix(" HTTP/1.1"))
+                .and_then(|id| id.parse::<u32>().ok());
+            if let Some(id) = subject_id {
+                return (200, serde_json::json!({"id": id, "name": format!("作品{}", id), "eps": 12}).to_string());
+            }
+            (404, r#"{"description": "not found"}"#.to_string())
+        })
+        .await;
+        let client = BangumiClient::new(&BangumiSettings {
+            base_url,
+            cache_ttl_hours: 0,
+            page_limit: 1,
+            ..BangumiSettings::default()
+        })?;
+
+        let results = collection_results(&client, "tester", &[CollectionStatus::Watching, CollectionStatus::Wish]).await?;
+        assert_eq!(results.iter().map(|r| r.bangumi_id.unwrap()).collect::<Vec<_>>(), vec![10, 11, 12]);
+        assert_eq!(results[0].chinese_name.as_deref(), Some("中文10"));
+        assert_eq!(results[0].air_date, chrono::NaiveDate::from_ymd_opt(2025, 10, 3));
+        assert_eq!(results[0].match_reasoning.as_deref(), Some("来自Bangumi收藏（在看）"));
+        assert_eq!(results[2].match_reasoning.as_deref(), Some("来自Bangumi收藏（想看）"));
+        assert!(results.iter().all(|r| r.details.as_ref().is_some_and(|d| d.eps == 12)));
+        // 没有剧集列表时提示复核
+        assert!(results.iter().all(|r| !r.review_notes.is_empty()));
+
+        let task: Task = serde_json::from_str(
+            r#"{"description": "collection", "site": "BangumiCollection", "root_path": "/anime",
+                "collection": {"username": "tester", "statuses": ["watching", "wish"]}}"#,
+        )?;
+        assert_eq!(task.collection.statuses, vec![CollectionStatus::Watching, CollectionStatus::Wish]);
+        let rules = generate_qb_rules(&results, &task, &task.collection.season_name)?.rules;
+        assert!(rules.get("Bangumi收藏 中文12").is_some());
+        assert_eq!(rules.as_object().unwrap().len(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collection_update() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::meta_providers::bangumi::collection::update_collections;
+        use crate::models::{BangumiResult, BangumiSettings, CollectionUpdateSettings};
+        use std::sync::{Arc, Mutex};
+
+        let posts = Arc::new(Mutex::new(Vec::new()));
+        let recorded = posts.clone();
+        let base_url = spawn_mock_server(move |request| {
+            let first_line = request.lines().next().unwrap_or_default();
+            if first_line.starts_with("GET /v0/me ") {
+                (200, r#"{"username": "tester"}"#.to_string())
+            } else if first_line.starts_with("GET /v0/users/tester/collections?subject_type=2") {
+                let data = serde_json::json!([{"subject_id": 20, "type": 2}]);
+                (200, serde_json::json!({"total": 1, "limit": 20, "offset": 0, "data": data}).to_string())
+            } else if first_line.starts_with("POST /v0/users/-/collections/") {
+                recorded.lock().unwrap().push(request.to_string());
+                if first_line.contains("/22 ") {
+                    (400, r#"{"description": "subject not found"}"#.to_string())
+                } else {
+                    (202, "{}".to_string())
+                }
+            } else {
+                (404, r#"{"description": "not found"}"#.to_string())
+            }
+        })
+        .await;
+        let settings = BangumiSettings {
+            base_url,
+            access_token: Some("token".to_string()),
+            cache_ttl_hours: 0,
+            max_retries: 0,
+            ..BangumiSettings::default()
+        };
+        let client = BangumiClient::new(&settings)?;
+
+        let result = |bangumi_id: Option<u32>| -> BangumiResult {
+            let mut result: BangumiResult = serde_json::from_str(
+                r#"{"original_title": "作品", "cleaned_title": "作品", "bangumi_id": null, "chinese_name": null,
+                    "aliases": [], "air_date": null, "keywords": []}"#,
+            )
+            .unwrap();
+            result.bangumi_id = bangumi_id;
+            result
+        };
+        let results = vec![result(Some(20)), result(Some(21)), result(Some(21)), result(Some(22)), result(None)];
+
+        // 试运行只列出未收藏的作品
+        let dry_run = CollectionUpdateSettings { enabled: true, dry_run: true, ..CollectionUpdateSettings::default() };
+        let report = update_collections(&client, &results, &dry_run).await?;
+        assert_eq!(report.updated, vec![21, 22]);
+        assert_eq!(report.skipped, vec![20]);
+        assert!(posts.lock().unwrap().is_empty());
+
+        // 实际更新时设置为在看，失败的条目单独列出
+        let update = CollectionUpdateSettings { dry_run: false, ..dry_run };
+        let report = update_collections(&client, &results, &update).await?;
+        assert_eq!(report.updated, vec![21]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, 22);
+        {
+            let posts = posts.lock().unwrap();
+            assert_eq!(posts.len(), 2);
+            assert!(posts[0].contains("{\"type\":3}"));
+            assert!(posts[0].to_lowercase().contains("authorization: bearer token"));
+        }
+
+        // 没有访问令牌时不更新；配置为空字符串，不受环境变量 BANGUMI_ACCESS_TOKEN 影响
+        let client = BangumiClient::new(&BangumiSettings { access_token: Some(String::new()), ..settings })?;
+        assert!(!client.is_authenticated());
+        assert!(update_collections(&client, &results, &update).await.is_err());
+        Ok(())
+    }
+}
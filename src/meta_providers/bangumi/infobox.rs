@@ -0,0 +1,172 @@
+use crate::models::{BangumiInfoboxItem, InfoboxInfo};
+use chrono::NaiveDate;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// 作为别名使用的infobox字段
+const ALIAS_KEYS: &[&str] = &["别名", "中文名", "译名", "英文名"];
+
+/// 放送日期所在的infobox字段，按优先级排列
+const DATE_KEYS: &[&str] = &["放送开始", "开始", "上映年度", "发售日"];
+
+/// 解析infobox中的常用字段
+pub fn parse_infobox(infobox: &[BangumiInfoboxItem]) -> InfoboxInfo {
+    let mut info = InfoboxInfo::default();
+
+    for item in infobox {
+        let values = infobox_values(&item.value);
+        let key = item.key.trim();
+
+        if ALIAS_KEYS.contains(&key) {
+            for (label, value) in &values {
+                push_unique(&mut info.aliases, value);
+                if key == "英文名" || label.as_deref().is_some_and(|label| label.contains("英")) {
+                    push_unique(&mut info.english_names, value);
+                }
+            }
+            continue;
+        }
+
+        let mut texts = values.into_iter().map(|(_, value)| value);
+        match key {
+            "官方网站" if info.official_site.is_none() => info.official_site = texts.next(),
+            "放送星期" if info.broadcast_weekday.is_none() => info.broadcast_weekday = texts.next(),
+            "话数" if info.episode_count.is_none() => info.episode_count = texts.find_map(|text| parse_count(&text)),
+            "播放电视台" => {
+                for station in texts.flat_map(|text| split_list(&text)) {
+                    push_unique(&mut info.tv_stations, &station);
+                }
+            }
+            "动画制作" => {
+                for studio in texts.flat_map(|text| split_list(&text)) {
+                    push_unique(&mut info.studios, &studio);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    info.air_date = DATE_KEYS.iter().find_map(|date_key| {
+        infobox
+            .iter()
+            .filter(|item| item.key.trim() == *date_key)
+            .flat_map(|item| infobox_values(&item.value))
+            .find_map(|(_, value)| parse_infobox_date(&value))
+    });
+
+    info
+}
+
+/// 解析infobox中的日期
+///
+/// 支持 `2025-10-05`、`2025年10月5日(日)`、`2025/10/5`、`2025.10.05` 以及只有年月的 `2025-10`、`2025年10月`，
+/// 只有年月时取该月第一天。
+pub fn parse_infobox_date(text: &str) -> Option<NaiveDate> {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    let regex = REGEX.get_or_init(|| {
+        Regex::new(r"(\d{4})\s*[年\-/.]\s*(\d{1,2})\s*月?(?:\s*[\-/.]?\s*(\d{1,2})\s*日?)?").unwrap()
+    });
+
+    let captures = regex.captures(text)?;
+    let year = captures[1].parse().ok()?;
+    let month = captures[2].parse().ok()?;
+    let day = captures.get(3).map_or(Some(1), |day| day.as_str().parse().ok())?;
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// 展开infobox的值：字符串，或 `{"k": 标签, "v": 值}` / 字符串组成的数组
+fn infobox_values(value: &serde_json::Value) -> Vec<(Option<String>, String)> {
+    let mut values = Vec::new();
+    let mut push = |label: Option<&str>, text: &str| {
+        let text = text.trim();
+        if !text.is_empty() {
+            values.push((label.map(|label| label.to_string()), text.to_string()));
+        }
+    };
+
+    match value {
+        serde_json::Value::String(text) => push(None, text),
+        serde_json::Value::Array(items) => {
+            for item in items {
+                match item {
+                    serde_json::Value::String(text) => push(None, text),
+                    serde_json::Value::Object(obj) => {
+                        if let Some(serde_json::Value::String(text)) = obj.get("v") {
+                            push(obj.get("k").and_then(|k| k.as_str()), text);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+    values
+}
+
+/// 拆分用顿号、斜杠等分隔的列表
+fn split_list(text: &str) -> Vec<String> {
+    text.split(['、', '/', '／', ',', '，'])
+        .map(|part| part.trim().to_string())
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// 解析话数，如 `12`、`全24话`
+fn parse_count(text: &str) -> Option<u32> {
+    let digits: String = text
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok().filter(|&count| count > 0)
+}
+
+fn push_unique(list: &mut Vec<String>, value: &str) {
+    if !list.iter().any(|existing| existing == value) {
+        list.push(value.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_infobox_parsing() {
+        use crate::meta_providers::bangumi::infobox::{parse_infobox, parse_infobox_date};
+        use crate::models::BangumiInfoboxItem;
+        use chrono::NaiveDate;
+
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d);
+        assert_eq!(parse_infobox_date("2025-10-05"), date(2025, 10, 5));
+        assert_eq!(parse_infobox_date("2025年10月5日(日)"), date(2025, 10, 5));
+        assert_eq!(parse_infobox_date("2025/1/9"), date(2025, 1, 9));
+        assert_eq!(parse_infobox_date("2025.01.09"), date(2025, 1, 9));
+        assert_eq!(parse_infobox_date("2025年10月"), date(2025, 10, 1));
+        assert_eq!(parse_infobox_date("2025-10"), date(2025, 10, 1));
+        assert_eq!(parse_infobox_date("未定"), None);
+
+        let infobox: Vec<BangumiInfoboxItem> = serde_json::from_str(
+            r#"[
+                {"key": "中文名", "value": "示例动画"},
+                {"key": "别名", "value": [{"v": "Example"}, {"k": "英文名", "v": "Example Anime"}, "例"]},
+                {"key": "话数", "value": "全12话"},
+                {"key": "放送开始", "value": "2025年10月5日(日)"},
+                {"key": "放送星期", "value": "星期日"},
+                {"key": "官方网站", "value": "https://example.com/"},
+                {"key": "播放电视台", "value": "TOKYO MX、BS11"},
+                {"key": "动画制作", "value": "A社／B社"}
+            ]"#,
+        )
+        .unwrap();
+
+        let info = parse_infobox(&infobox);
+        assert_eq!(info.aliases, vec!["示例动画", "Example", "Example Anime", "例"]);
+        assert_eq!(info.english_names, vec!["Example Anime"]);
+        assert_eq!(info.episode_count, Some(12));
+        assert_eq!(info.air_date, date(2025, 10, 5));
+        assert_eq!(info.broadcast_weekday.as_deref(), Some("星期日"));
+        assert_eq!(info.official_site.as_deref(), Some("https://example.com/"));
+        assert_eq!(info.tv_stations, vec!["TOKYO MX", "BS11"]);
+        assert_eq!(info.studios, vec!["A社", "B社"]);
+    }
+}
@@ -1,14 +1,43 @@
-use crate::models::{AnimeWork, BangumiResult, BangumiSubject, AiConfig};
-use crate::ai::object_matcher::{CandidateWork, batch_process_searches};
+use crate::models::{
+    AirDateWindow, AnimeWork, BangumiEpisode, BangumiRelatedSubject, BangumiResult, BangumiSubject, BangumiSubjectDetail, AiConfig,
+    EpisodeRange, InfoboxInfo, MatchSettings,
+};
+use crate::ai::object_matcher::fuzzy::prune_candidates;
+use crate::ai::object_matcher::{BatchMatchResult, CandidateWork, match_search_tasks};
+use crate::overrides::Overrides;
+use crate::review::{REVIEW_CANDIDATE_LIMIT, ReviewDecision, ReviewEntry, ReviewQueue};
 use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone};
+use futures::stream::{self, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// 沿前传关系向前查找的最大条目数
+const MAX_PREQUEL_DEPTH: usize = 8;
+
+pub mod cache;
+pub mod client;
+pub mod collection;
+pub mod infobox;
+
+pub use client::BangumiClient;
 
 pub async fn search_bangumi_for_works(
+    client: &BangumiClient,
     works: &[AnimeWork],
+    ai_config: &AiConfig,
+    settings: &MatchSettings,
 ) -> Result<Vec<BangumiResult>, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
     let mut results = Vec::new();
 
+    // 应用覆盖文件：跳过指定的作品，追加指定的关键词
+    let overrides = Overrides::load(&settings.override_path())?;
+    let works = &overrides.apply(works)[..];
+
+    // 读取复核队列，已做出决定的作品不再搜索和匹配
+    let review_path = Path::new(&settings.review_file);
+    let mut review_queue = ReviewQueue::load(review_path)?;
+
     // 创建批量搜索进度条
     let total_works = works.len();
     let search_pb = ProgressBar::new(total_works as u64);
@@ -22,76 +51,84 @@ pub async fn search_bangumi_for_works(
     search_pb.enable_steady_tick(std::time::Duration::from_millis(250));
     search_pb.set_message("Bangumi批量搜索中...");
 
-    // 准备批量搜索任务
-    let mut search_tasks = Vec::new();
-    let mut work_indices = Vec::new();
-
+    // 准备每个作品的搜索关键词：包含cleaned_title和keywords，并去重
+    let mut pending_works: Vec<(usize, Vec<&str>)> = Vec::new();
     for (index, work) in works.iter().enumerate() {
-        // 限制作品名称长度，避免进度条消息过长导致渲染问题
-        let display_title = if work.cleaned_title.chars().count() > 20 {
-            let truncated: String = work.cleaned_title.chars().take(17).collect();
-            format!("{}...", truncated)
-        } else {
-            work.cleaned_title.clone()
-        };
-
-        search_pb.set_message(format!(
-            "搜索作品: {} ({}/{})",
-            display_title,
-            index + 1,
-            total_works
-        ));
+        let fixed_id = overrides.find(work).and_then(|entry| entry.bangumi_id);
+        if fixed_id.is_some() || review_queue.decided(&work.original_title).is_some() {
+            search_pb.inc(1);
+            continue;
+        }
 
-        // 构建搜索关键词数组：包含cleaned_title和keywords，并去重
         let mut search_keywords: Vec<&str> = Vec::new();
         search_keywords.push(&work.cleaned_title);
         search_keywords.extend(work.keywords.iter().map(|s| s.as_str()));
-
-        // 去重
         search_keywords.sort();
         search_keywords.dedup();
+        pending_works.push((index, search_keywords));
+    }
 
-        // 收集所有候选作品，按Bangumi ID去重
-        let mut all_candidate_works: Vec<CandidateWork> = Vec::new();
-
-        for keyword in search_keywords {
-            match search_bangumi_with_keyword(&client, keyword, &work.air_date).await {
-                Ok(subjects) => {
-                    if !subjects.is_empty() {
-                        log::debug!("关键词 '{}' 找到 {} 个候选作品", keyword, subjects.len());
-                        // 添加候选作品到集合中
-                        for subject in subjects {
-                            let candidate = CandidateWork::from(&subject);
-                            // 检查是否已存在相同ID的候选作品
-                            if !all_candidate_works.iter().any(|c| c.bangumi_id == candidate.bangumi_id) {
-                                all_candidate_works.push(candidate);
-                            }
-                        }
-                    } else {
-                        log::debug!("关键词 '{}' 未找到候选作品", keyword);
-                    }
-                }
-                Err(e) => {
-                    log::warn!("搜索关键词 '{}' 时发生错误: {}", keyword, e);
-                    // 继续处理其他关键词，不中断整个流程
+    // 按放送日期范围逐轮搜索，没有候选作品的作品在下一轮扩大范围
+    let windows = settings.air_date_windows();
+    let mut candidates_by_work: Vec<Vec<CandidateWork>> = vec![Vec::new(); pending_works.len()];
+    let mut window_by_work: Vec<Option<AirDateWindow>> = vec![None; works.len()];
+    let mut remaining_slots: Vec<usize> = (0..pending_works.len()).collect();
+
+    for (round, &window) in windows.iter().enumerate() {
+        if remaining_slots.is_empty() {
+            break;
+        }
+        let last_round = round + 1 == windows.len();
+        let round_works: Vec<&(usize, Vec<&str>)> = remaining_slots.iter().map(|&slot| &pending_works[slot]).collect();
+        let round_candidates = search_round(client, works, &round_works, window, last_round, &search_pb).await;
+
+        let mut empty_slots = Vec::new();
+        for (slot, (candidates, failed)) in remaining_slots.into_iter().zip(round_candidates) {
+            let index = pending_works[slot].0;
+            let work = &works[index];
+            if candidates.is_empty() {
+                // 搜索出错时扩大范围也无济于事
+                if work.air_date.is_some() && !last_round && !failed {
+                    empty_slots.push(slot);
                 }
+                continue;
             }
-        }
 
-        // 如果有候选作品，创建一个搜索任务
-        if !all_candidate_works.is_empty() {
-            search_tasks.push((work.clone(), all_candidate_works));
-            work_indices.push(index);
+            if round > 0 {
+                log::info!(
+                    "'{}' 在较小的放送日期范围内未找到候选作品，扩大到{}后找到 {} 个",
+                    work.cleaned_title,
+                    window,
+                    candidates.len()
+                );
+            }
+            window_by_work[index] = Some(if work.air_date.is_some() { window } else { AirDateWindow::Unlimited });
+            candidates_by_work[slot] = candidates;
         }
+        remaining_slots = empty_slots;
+    }
 
-        // 更新搜索进度条
-        search_pb.inc(1);
+    // 有候选作品的作品按本地评分剪枝后创建一个搜索任务
+    let mut search_tasks = Vec::new();
+    let mut work_indices = Vec::new();
+    for ((index, _), mut all_candidate_works) in pending_works.iter().zip(candidates_by_work) {
+        if all_candidate_works.is_empty() {
+            continue;
+        }
+        let work = &works[*index];
+        let found = all_candidate_works.len();
+        prune_candidates(work, &mut all_candidate_works, settings.max_candidates);
+        if found > all_candidate_works.len() {
+            log::debug!("'{}' 的 {} 个候选作品剪枝为 {} 个", work.cleaned_title, found, all_candidate_works.len());
+        }
+        search_tasks.push((work.clone(), all_candidate_works));
+        work_indices.push(*index);
     }
 
     // 完成搜索进度条
     search_pb.finish_with_message("Bangumi搜索完成");
 
-    // 创建AI批量匹配进度条
+    // 创建批量匹配进度条
     let ai_pb = ProgressBar::new(search_tasks.len() as u64);
     ai_pb.set_style(
         ProgressStyle::with_template(
@@ -101,79 +138,378 @@ pub async fn search_bangumi_for_works(
         .progress_chars("█▓▒░")
     );
     ai_pb.enable_steady_tick(std::time::Duration::from_millis(250));
-    ai_pb.set_message("AI批量匹配中...");
+    ai_pb.set_message("批量匹配中...");
 
-    // 使用批量AI匹配
-    let ai_config = AiConfig::deepseek();
+    // 按配置的匹配方式（AI/本地/混合）批量匹配
     let batch_size = 10; // 每批次10个任务
-    let matched_ids = batch_process_searches(&search_tasks, &ai_config, batch_size, Some(&ai_pb)).await?;
+    let match_results =
+        match_search_tasks(&search_tasks, ai_config, settings, batch_size, Some(&ai_pb)).await?;
 
     // 处理匹配结果
     for (index, work) in works.iter().enumerate() {
-        let mut found = false;
+        let mut result = BangumiResult {
+            original_title: work.original_title.clone(),
+            cleaned_title: work.cleaned_title.clone(),
+            bangumi_id: None,
+            chinese_name: None,
+            aliases: Vec::new(),
+            air_date: work.air_date,
+            keywords: work.keywords.clone(),
+            match_confidence: None,
+            match_reasoning: None,
+            candidate_count: 0,
+            review_notes: Vec::new(),
+            cleaned_by: work.cleaned_by,
+            details: None,
+            episode_range: None,
+            prequels: Vec::new(),
+            air_date_window: window_by_work[index],
+            info: InfoboxInfo::default(),
+        };
+
+        // 覆盖文件指定的ID优先于复核决定
+        if let Some(bangumi_id) = overrides.find(work).and_then(|entry| entry.bangumi_id) {
+            result.bangumi_id = Some(bangumi_id);
+            result.match_confidence = Some(1.0);
+            result.match_reasoning = Some(format!("覆盖文件指定 Bangumi ID {}", bangumi_id));
+            results.push(result);
+            continue;
+        }
+
+        // 应用之前运行中记录的复核决定
+        if let Some(entry) = review_queue.decided(&work.original_title) {
+            apply_review_decision(&mut result, entry);
+            results.push(result);
+            continue;
+        }
 
         // 查找该作品的匹配结果
-        for (task_index, &work_index) in work_indices.iter().enumerate() {
-            if work_index == index
-                && let Some(bangumi_id) = matched_ids[task_index]
-            {
-                // 找到匹配，创建BangumiResult
-                // 从候选作品中提取详细信息
-                let search_task = &search_tasks[task_index];
-                let candidate_works = &search_task.1;
-
-                // 查找匹配的候选作品
-                if let Some(matched_candidate) = candidate_works.iter().find(|c| c.bangumi_id == bangumi_id) {
-                    let chinese_name = if !matched_candidate.chinese_title.is_empty() {
-                        Some(matched_candidate.chinese_title.clone())
-                    } else {
-                        None
-                    };
-
-                    results.push(BangumiResult {
+        if let Some(task_index) = work_indices.iter().position(|&work_index| work_index == index) {
+            let candidate_works = &search_tasks[task_index].1;
+            result.candidate_count = candidate_works.len();
+
+            if let Some(match_result) = &match_results[task_index] {
+                result.match_confidence = Some(match_result.confidence);
+                result.match_reasoning = Some(match_result.reasoning.clone());
+
+                // 查找匹配的候选作品，从中提取详细信息；ID不在候选作品中时视为未匹配
+                let matched_candidate = matched_candidate(match_result, candidate_works);
+                if let Some(bangumi_id) = match_result.matched_bangumi_id
+                    && matched_candidate.is_none()
+                {
+                    log::warn!("'{}' 的匹配结果ID {} 不在候选作品中，视为未匹配", work.cleaned_title, bangumi_id);
+                } else if let Some(matched_candidate) = matched_candidate
+                    && match_result.accepted_id(settings.accept_threshold).is_some()
+                {
+                    result.bangumi_id = Some(matched_candidate.bangumi_id);
+                    fill_from_candidate(&mut result, matched_candidate);
+                } else if let Some(bangumi_id) = match_result.accepted_id(settings.review_threshold) {
+                    // 处于复核区间：暂不采纳，加入复核队列
+                    result.review_notes.push(format!(
+                        "匹配置信度 {:.2} 低于采纳阈值 {:.2}，建议ID {} 待人工复核",
+                        match_result.confidence, settings.accept_threshold, bangumi_id
+                    ));
+                    review_queue.enqueue(ReviewEntry {
                         original_title: work.original_title.clone(),
                         cleaned_title: work.cleaned_title.clone(),
-                        bangumi_id: Some(bangumi_id),
-                        chinese_name,
-                        aliases: matched_candidate.aliases.clone(),
                         air_date: work.air_date,
-                        keywords: work.keywords.clone(),
+                        bangumi_id: Some(bangumi_id),
+                        confidence: match_result.confidence,
+                        reasoning: match_result.reasoning.clone(),
+                        candidates: top_review_candidates(candidate_works, bangumi_id),
+                        decision: ReviewDecision::Pending,
                     });
-
-                    found = true;
-                    break;
                 }
             }
         }
 
-        if !found {
-            results.push(BangumiResult {
-                original_title: work.original_title.clone(),
-                cleaned_title: work.cleaned_title.clone(),
-                bangumi_id: None,
-                chinese_name: None,
-                aliases: Vec::new(),
-                air_date: work.air_date,
-                keywords: work.keywords.clone(),
-            });
+        results.push(result);
+    }
+
+    // 并发获取已匹配作品的条目详情、剧集列表和前传链
+    ai_pb.set_message("获取条目详情和剧集列表...");
+    fetch_result_details(client, &mut results).await;
+
+    if !review_queue.entries.is_empty() {
+        review_queue.save(review_path)?;
+        let pending = review_queue.pending_count();
+        if pending > 0 {
+            log::info!("{} 个作品待人工复核，请编辑 {}", pending, review_path.display());
         }
     }
 
+    client.save_cache();
+
     // 完成进度条
-    ai_pb.finish_with_message("AI批量匹配完成");
+    ai_pb.finish_with_message("批量匹配完成");
 
     Ok(results)
 }
 
-pub async fn search_bangumi_with_keyword(
-    client: &reqwest::Client,
+/// 用一个放送日期范围搜索一轮作品，返回每个作品按Bangumi ID去重后的候选作品，
+/// 以及是否有关键词搜索出错
+///
+/// 所有关键词搜索共用一个有界并发池。作品的最后一个关键词完成时，
+/// 如果找到了候选作品、搜索出错、没有放送日期或已是最后一轮，就推进进度条。
+async fn search_round(
+    client: &BangumiClient,
+    works: &[AnimeWork],
+    round_works: &[&(usize, Vec<&str>)],
+    window: AirDateWindow,
+    last_round: bool,
+    search_pb: &ProgressBar,
+) -> Vec<(Vec<CandidateWork>, bool)> {
+    let remaining_searches: Vec<AtomicUsize> = round_works
+        .iter()
+        .map(|(_, keywords)| AtomicUsize::new(keywords.len()))
+        .collect();
+    let found: Vec<AtomicBool> = round_works.iter().map(|_| AtomicBool::new(false)).collect();
+    let failed: Vec<AtomicBool> = round_works.iter().map(|_| AtomicBool::new(false)).collect();
+    let searches = round_works.iter().enumerate().flat_map(|(slot, (index, keywords))| {
+        keywords.iter().map(move |keyword| (slot, *index, *keyword))
+    });
+
+    let keyword_results: Vec<(usize, Vec<BangumiSubject>)> = stream::iter(searches)
+        .map(|(slot, index, keyword)| {
+            let remaining_searches = &remaining_searches;
+            let found = &found;
+            let failed = &failed;
+            async move {
+                let work = &works[index];
+                let subjects = match search_bangumi_with_window(client, keyword, &work.air_date, window).await {
+                    Ok(subjects) => {
+                        if subjects.is_empty() {
+                            log::debug!("关键词 '{}' 在{}内未找到候选作品", keyword, window);
+                        } else {
+                            log::debug!("关键词 '{}' 在{}内找到 {} 个候选作品", keyword, window, subjects.len());
+                            found[slot].store(true, Ordering::SeqCst);
+                        }
+                        subjects
+                    }
+                    Err(e) => {
+                        // 继续处理其他关键词，不中断整个流程
+                        log::warn!("搜索关键词 '{}' 时发生错误: {}", keyword, e);
+                        failed[slot].store(true, Ordering::SeqCst);
+                        Vec::new()
+                    }
+                };
+
+                let work_done = remaining_searches[slot].fetch_sub(1, Ordering::SeqCst) == 1;
+                let finished = found[slot].load(Ordering::SeqCst)
+                    || failed[slot].load(Ordering::SeqCst)
+                    || work.air_date.is_none()
+                    || last_round;
+                if work_done && finished {
+                    search_pb.inc(1);
+                    search_pb.set_message(format!("已搜索: {}", display_title(&work.cleaned_title)));
+                }
+                (slot, subjects)
+            }
+        })
+        .buffered(client.concurrency)
+        .collect()
+        .await;
+
+    // 收集每个作品的候选作品，按Bangumi ID去重
+    let mut candidates_by_work: Vec<Vec<CandidateWork>> = vec![Vec::new(); round_works.len()];
+    for (slot, subjects) in keyword_results {
+        let all_candidate_works = &mut candidates_by_work[slot];
+        for subject in subjects {
+            let candidate = CandidateWork::from(&subject);
+            if !all_candidate_works.iter().any(|c| c.bangumi_id == candidate.bangumi_id) {
+                all_candidate_works.push(candidate);
+            }
+        }
+    }
+    candidates_by_work
+        .into_iter()
+        .zip(failed)
+        .map(|(candidates, failed)| (candidates, failed.into_inner()))
+        .collect()
+}
+
+/// 限制作品名称长度，避免进度条消息过长导致渲染问题
+fn display_title(title: &str) -> String {
+    if title.chars().count() > 20 {
+        let truncated: String = title.chars().take(17).collect();
+        format!("{}...", truncated)
+    } else {
+        title.to_string()
+    }
+}
+
+/// 用候选作品的信息填充匹配结果
+fn fill_from_candidate(result: &mut BangumiResult, candidate: &CandidateWork) {
+    result.chinese_name = if !candidate.chinese_title.is_empty() {
+        Some(candidate.chinese_title.clone())
+    } else {
+        None
+    };
+    result.aliases = candidate.aliases.clone();
+    result.info = candidate.info.clone();
+}
+
+/// 用条目详情补充匹配结果：完整infobox中的别名、中文名、放送日期和其他常用字段
+pub fn apply_subject_detail(result: &mut BangumiResult, detail: BangumiSubjectDetail) {
+    if result.chinese_name.is_none() && !detail.name_cn.is_empty() {
+        result.chinese_name = Some(detail.name_cn.clone());
+    }
+    let info = infobox::parse_infobox(&detail.infobox);
+    for alias in &info.aliases {
+        if !result.aliases.contains(alias) {
+            result.aliases.push(alias.clone());
+        }
+    }
+    if result.air_date.is_none() {
+        result.air_date = info.air_date;
+    }
+    result.info = info;
+    result.details = Some(detail);
+}
+
+/// 并发获取已匹配作品的条目详情、剧集列表和前传链
+///
+/// 获取失败时只记录警告；没有剧集列表的作品加入复核提示。
+pub async fn fetch_result_details(client: &BangumiClient, results: &mut [BangumiResult]) {
+    let matched_ids: Vec<Option<u32>> = results.iter().map(|result| result.bangumi_id).collect();
+    let fetched: Vec<_> = stream::iter(matched_ids)
+        .map(|bangumi_id| async move {
+            match bangumi_id {
+                Some(id) => Some((
+                    id,
+                    client.get_subject(id).await,
+                    client.get_episodes(id).await,
+                    fetch_prequel_chain(client, id).await,
+                )),
+                None => None,
+            }
+        })
+        .buffered(client.concurrency)
+        .collect()
+        .await;
+
+    for (result, fetched) in results.iter_mut().zip(fetched) {
+        let Some((bangumi_id, detail, episodes, prequels)) = fetched else {
+            continue;
+        };
+        match detail {
+            Ok(detail) => apply_subject_detail(result, detail),
+            Err(e) => log::warn!("获取条目 {} 的详情失败: {}", bangumi_id, e),
+        }
+        match episodes {
+            Ok(episodes) => {
+                let eps = result.details.as_ref().map(|detail| detail.eps).unwrap_or(0);
+                result.episode_range = episode_range(&episodes, eps);
+            }
+            Err(e) => log::warn!("获取条目 {} 的剧集列表失败: {}", bangumi_id, e),
+        }
+        match prequels {
+            Ok(prequels) => result.prequels = prequels,
+            Err(e) => log::warn!("获取条目 {} 的关联条目失败: {}", bangumi_id, e),
+        }
+        if result.episode_range.is_none() {
+            result
+                .review_notes
+                .push("Bangumi没有该作品的剧集列表，规则无法按集数区分前作，请检查".to_string());
+        }
+    }
+}
+
+/// 沿“前传”关系向前查找动画条目，返回前传链，最近的一部在前
+pub async fn fetch_prequel_chain(
+    client: &BangumiClient,
+    subject_id: u32,
+) -> Result<Vec<BangumiRelatedSubject>, Box<dyn std::error::Error>> {
+    let mut chain: Vec<BangumiRelatedSubject> = Vec::new();
+    let mut current_id = subject_id;
+
+    while chain.len() < MAX_PREQUEL_DEPTH {
+        let related = client.get_related_subjects(current_id).await?;
+        let Some(prequel) = related.into_iter().find(|subject| {
+            subject.relation == "前传"
+                && subject.subject_type == 2
+                && subject.id != subject_id
+                && !chain.iter().any(|known| known.id == subject.id)
+        }) else {
+            break;
+        };
+        current_id = prequel.id;
+        chain.push(prequel);
+    }
+
+    Ok(chain)
+}
+
+/// 根据本篇剧集的 `sort` 编号确定集数范围
+///
+/// 跳过0集和12.5这类总集篇编号；条目话数多于已列出的剧集时，范围不设结尾。
+pub fn episode_range(episodes: &[BangumiEpisode], eps: u32) -> Option<EpisodeRange> {
+    let numbers: Vec<u32> = episodes
+        .iter()
+        .filter(|episode| episode.episode_type == 0 && episode.sort >= 1.0 && episode.sort.fract() == 0.0)
+        .map(|episode| episode.sort as u32)
+        .collect();
+    let first = *numbers.iter().min()?;
+    let last = *numbers.iter().max()?;
+
+    let listed = last - first + 1;
+    Some(EpisodeRange {
+        first,
+        last: (eps <= listed).then_some(last),
+    })
+}
+
+/// 应用人工复核的决定
+fn apply_review_decision(result: &mut BangumiResult, entry: &ReviewEntry) {
+    result.candidate_count = entry.candidates.len();
+    match entry.decision {
+        ReviewDecision::Accept => {
+            result.bangumi_id = entry.bangumi_id;
+            result.match_confidence = Some(1.0);
+            result.match_reasoning = Some(format!(
+                "人工复核采纳（AI置信度 {:.2}: {}）",
+                entry.confidence, entry.reasoning
+            ));
+            if let Some(candidate) = entry.accepted_candidate() {
+                fill_from_candidate(result, candidate);
+            }
+        }
+        ReviewDecision::Reject => {
+            result.match_confidence = Some(0.0);
+            result.match_reasoning = Some("人工复核拒绝".to_string());
+        }
+        ReviewDecision::Pending => {}
+    }
+}
+
+/// 匹配结果中的ID对应的候选作品，ID不在候选作品中时返回None
+fn matched_candidate<'a>(match_result: &BatchMatchResult, candidate_works: &'a [CandidateWork]) -> Option<&'a CandidateWork> {
+    let bangumi_id = match_result.matched_bangumi_id?;
+    candidate_works.iter().find(|candidate| candidate.bangumi_id == bangumi_id)
+}
+
+/// 选出复核队列中展示的候选作品，AI建议的作品排在最前
+fn top_review_candidates(candidate_works: &[CandidateWork], suggested_id: u32) -> Vec<CandidateWork> {
+    let mut candidates: Vec<CandidateWork> = candidate_works
+        .iter()
+        .filter(|c| c.bangumi_id == suggested_id)
+        .chain(candidate_works.iter().filter(|c| c.bangumi_id != suggested_id))
+        .take(REVIEW_CANDIDATE_LIMIT)
+        .cloned()
+        .collect();
+    candidates.dedup_by_key(|c| c.bangumi_id);
+    candidates
+}
+
+/// 在放送日期前后指定范围内搜索关键词，没有放送日期或不限日期时不加日期过滤
+pub async fn search_bangumi_with_window(
+    client: &BangumiClient,
     keyword: &str,
     air_date: &Option<NaiveDate>,
+    window: AirDateWindow,
 ) -> Result<Vec<BangumiSubject>, Box<dyn std::error::Error>> {
-    let url = "https://api.bgm.tv/v0/search/subjects";
-
     // 构建日期范围查询
-    let date_range = build_air_date_filter(air_date);
+    let date_range = build_air_date_filter(air_date, window);
 
     // 构建POST请求体
     let mut request_body = serde_json::json!({
@@ -188,44 +524,28 @@ pub async fn search_bangumi_with_keyword(
     if let Some(ref date_filter) = date_range {
         request_body["filter"]["air_date"] = date_filter.clone();
     }
-
-
-    let response = client
-        .post(url)
-        .header("User-Agent", "smart_bangumi_qb_rule_generator/0.1.0")
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .send()
-        .await?;
-
-    if response.status().is_success() {
-        let json_response: serde_json::Value = response.json().await?;
-
-        if let Some(data_array) = json_response["data"].as_array() {
-            // 返回所有搜索结果，让批量处理来处理匹配
-            let subjects: Vec<BangumiSubject> = data_array
-                .iter()
-                .filter_map(|subject_data| {
-                    serde_json::from_value::<BangumiSubject>(subject_data.clone()).ok()
-                })
-                .collect();
-
-            return Ok(subjects);
-        }
+    client.search_filter.apply_to_body(&mut request_body);
+
+    // 返回符合过滤条件的搜索结果，让批量处理来处理匹配
+    let subjects = client.search_subjects(&request_body).await?;
+    let total = subjects.len();
+    let subjects: Vec<BangumiSubject> =
+        subjects.into_iter().filter(|subject| client.search_filter.accepts(subject)).collect();
+    if subjects.len() < total {
+        log::debug!("关键词 '{}' 的 {} 个搜索结果被过滤条件排除", keyword, total - subjects.len());
     }
-
-    Ok(Vec::new())
+    Ok(subjects)
 }
 
-fn build_air_date_filter(air_date: &Option<NaiveDate>) -> Option<serde_json::Value> {
+fn build_air_date_filter(air_date: &Option<NaiveDate>, window: AirDateWindow) -> Option<serde_json::Value> {
     // 根据放送时间构建日期范围过滤器
-    if let Some(date) = air_date {
+    if let (Some(date), AirDateWindow::Days(days)) = (air_date, window) {
         // 将NaiveDate转换为JST时区，确保日期范围正确
         let jst_date = convert_to_jst_date(*date);
 
-        // 对于具体日期，搜索前后100天的范围
-        let start_date = jst_date - chrono::Duration::days(100);
-        let end_date = jst_date + chrono::Duration::days(100);
+        // 对于具体日期，搜索前后指定天数的范围
+        let start_date = jst_date - chrono::Duration::days(days as i64);
+        let end_date = jst_date + chrono::Duration::days(days as i64);
 
         return Some(serde_json::json!([
             format!(">={}", start_date.format("%Y-%m-%d")),
@@ -244,36 +564,340 @@ fn convert_to_jst_date(naive_date: NaiveDate) -> DateTime<FixedOffset> {
         .unwrap()
 }
 
-pub(crate) fn extract_aliases_from_infobox(infobox: &[crate::models::BangumiInfoboxItem]) -> Vec<String> {
-    let mut aliases = Vec::new();
+#[cfg(test)]
+mod tests {
+    use crate::meta_providers::bangumi::{BangumiClient, search_bangumi_for_works};
+    use crate::models::{AiConfig, AnimeWork, MatchSettings};
+    use crate::test_support::spawn_mock_server;
+
+    #[tokio::test]
+    async fn test_subject_details_after_match() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::meta_providers::bangumi::apply_subject_detail;
+        use crate::models::{BangumiResult, BangumiSettings};
+
+        let base_url = spawn_mock_server(|request| {
+            if request.starts_with("GET /v0/subjects/454630 ") {
+                let body = serde_json::json!({
+                    "id": 454630,
+                    "name": "青のミブロ",
+                    "name_cn": "青之壬生浪",
+                    "platform": "TV",
+                    "date": "2024-10-19",
+                    "eps": 24,
+                    "total_episodes": 25,
+                    "rating": {"rank": 2100, "total": 800, "score": 6.8},
+                    "tags": [{"name": "新选组", "count": 120}, {"name": "漫画改", "count": 80}],
+                    "images": {"large": "https://lain.bgm.tv/pic/cover/l/a.jpg", "common": "", "medium": "", "small": "", "grid": ""},
+                    "infobox": [{"key": "别名", "value": [{"v": "Ao no Miburo"}]}, {"key": "话数", "value": "24"}]
+                });
+                (200, body.to_string())
+            } else {
+                (404, r#"{"title":"Not Found"}"#.to_string())
+            }
+        })
+        .await;
+        let client = BangumiClient::new(&BangumiSettings {
+            base_url,
+            cache_ttl_hours: 0,
+            ..BangumiSettings::default()
+        })?;
+
+        let detail = client.get_subject(454630).await?;
+        assert_eq!(detail.platform, "TV");
+        assert_eq!(detail.eps, 24);
+        assert_eq!(detail.rating.as_ref().map(|r| r.rank), Some(2100));
+        assert_eq!(detail.tags.len(), 2);
+        assert!(client.get_subject(1).await.is_err());
+
+        let mut result: BangumiResult = serde_json::from_str(
+            r#"{"original_title": "青のミブロ", "cleaned_title": "青のミブロ", "bangumi_id": 454630,
+                "chinese_name": null, "aliases": [], "air_date": null, "keywords": []}"#,
+        )?;
+        apply_subject_detail(&mut result, detail);
+        assert_eq!(result.chinese_name.as_deref(), Some("青之壬生浪"));
+        assert_eq!(result.aliases, vec!["Ao no Miburo"]);
+        assert_eq!(result.details.as_ref().map(|d| d.infobox.len()), Some(2));
+
+        // 详情随结果一起缓存
+        let cached: BangumiResult = serde_json::from_str(&serde_json::to_string(&result)?)?;
+        assert_eq!(cached.details.map(|d| d.total_episodes), Some(25));
+
+        Ok(())
+    }
 
-    for item in infobox {
-        if item.key == "别名" || item.key == "中文名" || item.key == "译名" {
-            match &item.value {
-                serde_json::Value::String(s) => {
-                    aliases.push(s.clone());
-                }
-                serde_json::Value::Array(arr) => {
-                    for val in arr {
-                        match val {
-                            serde_json::Value::String(s) => {
-                                aliases.push(s.clone());
-                            }
-                            serde_json::Value::Object(obj) => {
-                                if let Some(serde_json::Value::String(s)) = obj.get("v") {
-                                    aliases.push(s.clone());
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                _ => {}
+    #[tokio::test]
+    async fn test_concurrent_keyword_search() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::models::{BangumiSettings, MatchMode};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        // 第一次搜索返回503，之后按关键词返回候选作品
+        let search_count = Arc::new(AtomicUsize::new(0));
+        let counter = search_count.clone();
+        let base_url = spawn_mock_server(move |request| {
+            if !request.starts_with("POST /v0/search/subjects") {
+                return (404, r#"{"description": "not found"}"#.to_string());
+            }
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                return (503, r#"{"description": "busy"}"#.to_string());
+            }
+            let ids: &[u32] = if request.contains("\"keyword\":\"甲\"") {
+                &[1, 2]
+            } else if request.contains("\"keyword\":\"乙\"") {
+                &[2, 3]
+            } else {
+                &[]
+            };
+            let data: Vec<_> = ids
+                .iter()
+                .map(|id| serde_json::json!({"id": id, "name": format!("甲{}", id), "name_cn": "", "date": ""}))
+                .collect();
+            (200, serde_json::json!({"total": data.len(), "limit": 20, "offset": 0, "data": data}).to_string())
+        })
+        .await;
+
+        let settings = BangumiSettings {
+            base_url,
+            cache_ttl_hours: 0,
+            concurrency: 3,
+            requests_per_second: 50.0,
+            max_retries: 2,
+            ..BangumiSettings::default()
+        };
+        let client = BangumiClient::new(&settings)?;
+        let works = vec![
+            AnimeWork {
+                original_title: "甲".to_string(),
+                cleaned_title: "甲".to_string(),
+                keywords: vec!["乙".to_string(), "甲".to_string()],
+                ..Default::default()
+            },
+            AnimeWork {
+                original_title: "丙".to_string(),
+                cleaned_title: "丙".to_string(),
+                ..Default::default()
+            },
+        ];
+        let review_file = std::env::temp_dir().join(format!("brb_concurrent_review_{}.json", std::process::id()));
+        let match_settings = MatchSettings {
+            mode: MatchMode::Local,
+            review_file: review_file.to_string_lossy().into_owned(),
+            ..MatchSettings::default()
+        };
+
+        // 503后重试成功，两个关键词的候选按ID去重，结果顺序与输入一致
+        let results = search_bangumi_for_works(&client, &works, &AiConfig::deepseek(), &match_settings).await?;
+        assert_eq!(results.iter().map(|r| r.original_title.as_str()).collect::<Vec<_>>(), vec!["甲", "丙"]);
+        assert_eq!(results[0].candidate_count, 3);
+        assert_eq!(results[1].candidate_count, 0);
+        assert_eq!(search_count.load(Ordering::SeqCst), 4);
+
+        // 不重试时直接返回错误
+        search_count.store(0, Ordering::SeqCst);
+        let client = BangumiClient::new(&BangumiSettings { max_retries: 0, ..settings })?;
+        let error = client.search_subjects(&serde_json::json!({"keyword": "甲"})).await.unwrap_err().to_string();
+        assert!(error.contains("503"), "{}", error);
+
+        let _ = std::fs::remove_file(&review_file);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_episode_range_from_episodes() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::meta_providers::bangumi::episode_range;
+        use crate::models::{BangumiSettings, EpisodeRange};
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // 第二部分续播编号：本篇13-24集，另有总集篇12.5和SP；502的total异常偏大
+        let episode_requests = Arc::new(AtomicUsize::new(0));
+        let counter = episode_requests.clone();
+        let base_url = spawn_mock_server(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if request.starts_with("GET /v0/episodes?subject_id=502&type=0") {
+                let data = serde_json::json!([{"id": 9200, "type": 0, "sort": 1}]);
+                return (200, serde_json::json!({"total": 1000, "limit": 100, "offset": 0, "data": data}).to_string());
+            }
+            if !request.starts_with("GET /v0/episodes?subject_id=500&type=0") {
+                return (404, r#"{"description": "not found"}"#.to_string());
             }
+            let mut data: Vec<_> = (13..=24)
+                .map(|sort| serde_json::json!({"id": 9000 + sort, "type": 0, "sort": sort, "ep": sort - 12}))
+                .collect();
+            data.push(serde_json::json!({"id": 9100, "type": 0, "sort": 12.5}));
+            data.push(serde_json::json!({"id": 9101, "type": 1, "sort": 1}));
+            (200, serde_json::json!({"total": data.len(), "limit": 100, "offset": 0, "data": data}).to_string())
+        })
+        .await;
+        let client = BangumiClient::new(&BangumiSettings { base_url, cache_ttl_hours: 0, ..BangumiSettings::default() })?;
+
+        let episodes = client.get_episodes(500).await?;
+        assert_eq!(episodes.len(), 14);
+        let range = episode_range(&episodes, 12).unwrap();
+        assert_eq!(range, EpisodeRange { first: 13, last: Some(24) });
+
+        // 话数多于已列出的剧集时不设结尾
+        let open_range = episode_range(&episodes, 24).unwrap();
+        assert_eq!(open_range, EpisodeRange { first: 13, last: None });
+
+        // 没有剧集列表时不生成范围
+        assert!(episode_range(&[], 12).is_none());
+        assert!(client.get_episodes(501).await.is_err());
+
+        // 返回不满一页时不再翻页，即使total更大
+        episode_requests.store(0, Ordering::SeqCst);
+        assert_eq!(client.get_episodes(502).await?.len(), 1);
+        assert_eq!(episode_requests.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_air_date_window_widening() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::models::{AirDateWindow, BangumiSettings, MatchMode};
+        use std::sync::{Arc, Mutex};
+
+        // “远”只能在不限日期时找到，“近”在任何范围内都能找到
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let base_url = spawn_mock_server(move |request| {
+            let body = request.split("\r\n\r\n").nth(1).unwrap_or_default().to_string();
+            recorded.lock().unwrap().push(body.clone());
+            let found = body.contains("\"近\"") || !body.contains("air_date");
+            let data = if found {
+                serde_json::json!([{"id": 1, "name": "作品", "name_cn": "", "date": "2020-01-01"}])
+            } else {
+                serde_json::json!([])
+            };
+            (200, serde_json::json!({"total": data.as_array().unwrap().len(), "limit": 20, "offset": 0, "data": data}).to_string())
+        })
+        .await;
+        let client = BangumiClient::new(&BangumiSettings { base_url, cache_ttl_hours: 0, ..BangumiSettings::default() })?;
+
+        let air_date = chrono::NaiveDate::from_ymd_opt(2025, 10, 1);
+        let work = |title: &str, air_date: Option<chrono::NaiveDate>| AnimeWork {
+            original_title: title.to_string(),
+            cleaned_title: title.to_string(),
+            air_date,
+            ..Default::default()
+        };
+        let works = vec![work("远", air_date), work("近", air_date), work("无", None)];
+        let review_file = std::env::temp_dir().join(format!("brb_window_review_{}.json", std::process::id()));
+        let settings = MatchSettings {
+            mode: MatchMode::Local,
+            review_file: review_file.to_string_lossy().into_owned(),
+            air_date_window_days: 30,
+            ..MatchSettings::default()
+        };
+
+        let results = search_bangumi_for_works(&client, &works, &AiConfig::deepseek(), &settings).await?;
+        assert_eq!(results[0].air_date_window, Some(AirDateWindow::Unlimited));
+        assert_eq!(results[1].air_date_window, Some(AirDateWindow::Days(30)));
+        assert_eq!(results[2].air_date_window, Some(AirDateWindow::Unlimited));
+        {
+            let requests = requests.lock().unwrap();
+            let far: Vec<&String> = requests.iter().filter(|body| body.contains("\"远\"")).collect();
+            assert_eq!(far.len(), 3);
+            assert!(far[0].contains(">=2025-09-01"));
+            assert!(far[1].contains(">=2024-10-01"));
+            assert_eq!(requests.iter().filter(|body| body.contains("\"近\"")).count(), 1);
         }
+
+        // 不配置不限日期的范围时不会扩大到全部日期
+        let settings = MatchSettings { air_date_fallback_windows: vec![90], ..settings };
+        let results = search_bangumi_for_works(&client, &works[..1], &AiConfig::deepseek(), &settings).await?;
+        assert_eq!(results[0].air_date_window, None);
+        assert_eq!(results[0].candidate_count, 0);
+
+        let _ = std::fs::remove_file(&review_file);
+        Ok(())
     }
 
-    aliases
-}
+    #[tokio::test]
+    async fn test_search_filter() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::meta_providers::bangumi::search_bangumi_with_window;
+        use crate::models::{AirDateWindow, BangumiSettings, SearchFilter};
+        use std::sync::{Arc, Mutex};
+
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let recorded = bodies.clone();
+        let base_url = spawn_mock_server(move |request| {
+            let body = request.split("\r\n\r\n").nth(1).unwrap_or_default();
+            recorded.lock().unwrap().push(body.to_string());
+            let subject = |id: u32, platform: &str, nsfw: bool, tags: &[&str], score: f32| {
+                serde_json::json!({
+                    "id": id, "name": format!("作品{}", id), "name_cn": "", "platform": platform, "nsfw": nsfw,
+                    "tags": tags.iter().map(|name| serde_json::json!({"name": name, "count": 10})).collect::<Vec<_>>(),
+                    "rating": {"rank": 0, "total": 10, "score": score}
+                })
+            };
+            let data = vec![
+                subject(1, "TV", false, &["原创"], 7.2),
+                subject(2, "TV", true, &["原创"], 7.5),
+                subject(3, "其他", false, &["原创"], 8.0),
+                subject(4, "劇場版", false, &["原创"], 6.5),
+                subject(5, "WEB", false, &["漫画改"], 7.0),
+                subject(6, "OVA", false, &["原创"], 5.0),
+                subject(7, "", false, &[], 0.0),
+            ];
+            (200, serde_json::json!({"total": data.len(), "limit": 20, "offset": 0, "data": data}).to_string())
+        })
+        .await;
+
+        // 默认只排除NSFW条目和其他平台的条目
+        let client = BangumiClient::new(&BangumiSettings {
+            base_url: base_url.clone(),
+            cache_ttl_hours: 0,
+            ..BangumiSettings::default()
+        })?;
+        let subjects = search_bangumi_with_window(&client, "作品", &None, AirDateWindow::Unlimited).await?;
+        assert_eq!(subjects.iter().map(|s| s.id).collect::<Vec<_>>(), vec![1, 4, 5, 6, 7]);
+        assert_eq!(subjects[0].score(), Some(7.2));
+        assert_eq!(subjects[4].score(), None);
+        {
+            let body: serde_json::Value = serde_json::from_str(&bodies.lock().unwrap()[0])?;
+            assert_eq!(body["filter"]["nsfw"], false);
+            assert_eq!(body["filter"]["type"], serde_json::json!([2]));
+            assert!(body["filter"].get("tag").is_none());
+        }
+
+        // 标签和最低评分同时写入请求体并在本地过滤
+        let client = BangumiClient::new(&BangumiSettings {
+            base_url,
+            cache_ttl_hours: 0,
+            search_filter: SearchFilter {
+                nsfw: true,
+                platforms: vec!["TV".to_string(), "剧场版".to_string()],
+                tags: vec!["原创".to_string()],
+                min_rating: Some(6.0),
+            },
+            ..BangumiSettings::default()
+        })?;
+        let subjects = search_bangumi_with_window(&client, "作品", &None, AirDateWindow::Unlimited).await?;
+        assert_eq!(subjects.iter().map(|s| s.id).collect::<Vec<_>>(), vec![1, 2, 4]);
+        let body: serde_json::Value = serde_json::from_str(&bodies.lock().unwrap()[1])?;
+        assert!(body["filter"].get("nsfw").is_none());
+        assert_eq!(body["filter"]["tag"], serde_json::json!(["原创"]));
+        assert_eq!(body["filter"]["rating"], serde_json::json!([">=6"]));
+        Ok(())
+    }
 
+    #[test]
+    fn test_matched_candidate_must_be_listed() {
+        use crate::ai::object_matcher::BatchMatchResult;
+        use crate::meta_providers::bangumi::matched_candidate;
+        use crate::test_support::candidate;
+
+        let candidates = vec![candidate(1, "作品", None), candidate(2, "作品 第2期", None)];
+        let match_result = |bangumi_id| BatchMatchResult {
+            source_index: 0,
+            matched_bangumi_id: bangumi_id,
+            confidence: 0.95,
+            reasoning: String::new(),
+        };
 
+        assert_eq!(matched_candidate(&match_result(Some(2)), &candidates).map(|c| c.bangumi_id), Some(2));
+        // 候选作品之外的ID视为未匹配
+        assert!(matched_candidate(&match_result(Some(999)), &candidates).is_none());
+        assert!(matched_candidate(&match_result(None), &candidates).is_none());
+    }
+}
@@ -1,10 +1,18 @@
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::ai::budget::AiBudget;
+use crate::ai::prompts::PromptTemplates;
+use crate::utils::RateLimiter;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum SiteType {
     Kansou,
+    /// 不抓取季度表格，直接使用Bangumi用户收藏
+    BangumiCollection,
     // 预留未来支持的站点
     // ModelScope,
     // AnimeList,
@@ -14,6 +22,7 @@ impl std::fmt::Display for SiteType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SiteType::Kansou => write!(f, "kansou"),
+            SiteType::BangumiCollection => write!(f, "bangumi_collection"),
             // SiteType::ModelScope => write!(f, "modelscope"),
             // SiteType::AnimeList => write!(f, "animelist"),
         }
@@ -26,6 +35,7 @@ impl std::str::FromStr for SiteType {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "kansou" => Ok(SiteType::Kansou),
+            "bangumi_collection" => Ok(SiteType::BangumiCollection),
             // "modelscope" => Ok(SiteType::ModelScope),
             // "animelist" => Ok(SiteType::AnimeList),
             _ => Err(format!("不支持的站点类型: {}", s)),
@@ -41,6 +51,19 @@ pub enum AiProvider {
     // Claude,
 }
 
+impl AiProvider {
+    /// 各AI提供商的默认计费单价
+    pub fn default_pricing(&self) -> AiPricing {
+        match self {
+            AiProvider::DeepSeek => AiPricing {
+                input_per_million: 0.28,
+                output_per_million: 0.42,
+                currency: "USD".to_string(),
+            },
+        }
+    }
+}
+
 impl std::fmt::Display for AiProvider {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -56,6 +79,593 @@ pub struct Task {
     pub description: String,
     pub site: SiteType,
     pub root_path: String,
+    #[serde(default)]
+    pub ai: AiSettings,
+    #[serde(default)]
+    pub matching: MatchSettings,
+    #[serde(default)]
+    pub bangumi: BangumiSettings,
+    #[serde(default)]
+    pub collection: CollectionSettings,
+    #[serde(default)]
+    pub collection_update: CollectionUpdateSettings,
+    #[serde(default)]
+    pub qbittorrent: QBittorrentSettings,
+}
+
+/// tasks.json 中的qBittorrent设置：生成规则后通过WebUI API直接推送
+#[derive(Debug, Deserialize, Clone)]
+pub struct QBittorrentSettings {
+    /// 是否启用推送
+    #[serde(default)]
+    pub enabled: bool,
+    /// WebUI地址
+    #[serde(default = "default_qbittorrent_url")]
+    pub url: String,
+    /// WebUI用户名，未配置时不登录（WebUI对本机免登录时）
+    #[serde(default)]
+    pub username: Option<String>,
+    /// WebUI密码，未配置时读取环境变量 QBITTORRENT_PASSWORD
+    #[serde(default)]
+    pub password: Option<String>,
+    /// 只列出将要新建和覆盖的规则，不实际推送
+    #[serde(default)]
+    pub dry_run: bool,
+    /// 推送前在终端确认
+    #[serde(default = "default_qbittorrent_confirm")]
+    pub confirm: bool,
+    /// 与已有规则合并，保留运行时状态和用户修改；启用推送时从WebUI读取已有规则，否则读取 `existing_rules_file`
+    #[serde(default)]
+    pub merge_existing: bool,
+    /// 从qBittorrent导出的已有规则文件
+    #[serde(default)]
+    pub existing_rules_file: Option<String>,
+}
+
+impl QBittorrentSettings {
+    /// WebUI密码：优先使用配置，其次使用环境变量
+    pub fn password(&self) -> Option<String> {
+        self.password.clone().or_else(|| std::env::var("QBITTORRENT_PASSWORD").ok())
+    }
+}
+
+impl Default for QBittorrentSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: default_qbittorrent_url(),
+            username: None,
+            password: None,
+            dry_run: false,
+            confirm: default_qbittorrent_confirm(),
+            merge_existing: false,
+            existing_rules_file: None,
+        }
+    }
+}
+
+fn default_qbittorrent_url() -> String {
+    "http://localhost:8080".to_string()
+}
+
+fn default_qbittorrent_confirm() -> bool {
+    true
+}
+
+/// tasks.json 中的收藏更新设置：生成规则后把已匹配的作品加入Bangumi收藏
+#[derive(Debug, Deserialize, Clone)]
+pub struct CollectionUpdateSettings {
+    /// 是否启用，需要配置访问令牌
+    #[serde(default)]
+    pub enabled: bool,
+    /// 设置的收藏状态
+    #[serde(default = "default_collection_update_status")]
+    pub status: CollectionStatus,
+    /// 只列出将要更新的作品，不实际修改收藏
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl Default for CollectionUpdateSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            status: default_collection_update_status(),
+            dry_run: false,
+        }
+    }
+}
+
+fn default_collection_update_status() -> CollectionStatus {
+    CollectionStatus::Watching
+}
+
+/// tasks.json 中的Bangumi收藏设置，用于 `BangumiCollection` 站点
+#[derive(Debug, Deserialize, Clone)]
+pub struct CollectionSettings {
+    /// 用户名，为空时使用访问令牌所属的用户
+    #[serde(default)]
+    pub username: Option<String>,
+    /// 读取的收藏状态
+    #[serde(default = "default_collection_statuses")]
+    pub statuses: Vec<CollectionStatus>,
+    /// 规则的分类和保存目录名
+    #[serde(default = "default_collection_season_name")]
+    pub season_name: String,
+}
+
+impl Default for CollectionSettings {
+    fn default() -> Self {
+        Self {
+            username: None,
+            statuses: default_collection_statuses(),
+            season_name: default_collection_season_name(),
+        }
+    }
+}
+
+fn default_collection_statuses() -> Vec<CollectionStatus> {
+    vec![CollectionStatus::Wish, CollectionStatus::Watching]
+}
+
+fn default_collection_season_name() -> String {
+    "Bangumi收藏".to_string()
+}
+
+/// Bangumi收藏状态
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionStatus {
+    /// 想看
+    Wish,
+    /// 看过
+    Done,
+    /// 在看
+    Watching,
+    /// 搁置
+    OnHold,
+    /// 抛弃
+    Dropped,
+}
+
+impl CollectionStatus {
+    /// API中的收藏类型编号
+    pub fn api_type(&self) -> u8 {
+        match self {
+            Self::Wish => 1,
+            Self::Done => 2,
+            Self::Watching => 3,
+            Self::OnHold => 4,
+            Self::Dropped => 5,
+        }
+    }
+
+    pub fn from_api_type(api_type: u8) -> Option<Self> {
+        [Self::Wish, Self::Done, Self::Watching, Self::OnHold, Self::Dropped]
+            .into_iter()
+            .find(|status| status.api_type() == api_type)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Wish => "想看",
+            Self::Done => "看过",
+            Self::Watching => "在看",
+            Self::OnHold => "搁置",
+            Self::Dropped => "抛弃",
+        }
+    }
+}
+
+/// tasks.json 中的Bangumi API设置
+#[derive(Debug, Deserialize, Clone)]
+pub struct BangumiSettings {
+    /// API地址，可改为本地代理或测试服务
+    #[serde(default = "default_bangumi_base_url")]
+    pub base_url: String,
+    #[serde(default = "default_bangumi_user_agent")]
+    pub user_agent: String,
+    /// 每页请求的条数
+    #[serde(default = "default_bangumi_page_limit")]
+    pub page_limit: usize,
+    /// 每次搜索最多读取的条数
+    #[serde(default = "default_bangumi_max_results")]
+    pub max_results: usize,
+    /// 个人访问令牌，未配置时读取环境变量 BANGUMI_ACCESS_TOKEN；配置为空字符串时不使用令牌
+    #[serde(default)]
+    pub access_token: Option<String>,
+    /// 搜索结果和条目详情的缓存有效期（小时），为0时不使用缓存
+    #[serde(default = "default_bangumi_cache_ttl_hours")]
+    pub cache_ttl_hours: u64,
+    /// 缓存文件路径，默认为缓存目录下的 bangumi_cache.json
+    #[serde(default)]
+    pub cache_file: Option<String>,
+    /// 同时进行的搜索请求数
+    #[serde(default = "default_bangumi_concurrency")]
+    pub concurrency: usize,
+    /// 每秒最多发送的请求数，为0时不限制
+    #[serde(default = "default_bangumi_requests_per_second")]
+    pub requests_per_second: f64,
+    /// 遇到429或5xx响应时的最大重试次数
+    #[serde(default = "default_bangumi_max_retries")]
+    pub max_retries: u32,
+    /// 搜索候选作品时的过滤条件
+    #[serde(default)]
+    pub search_filter: SearchFilter,
+}
+
+impl BangumiSettings {
+    /// 相邻两次请求之间的最小间隔
+    pub fn request_interval(&self) -> Duration {
+        if self.requests_per_second > 0.0 {
+            Duration::from_secs_f64(1.0 / self.requests_per_second)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    /// 访问令牌：优先使用配置，其次使用环境变量；配置为空字符串时不读取环境变量
+    pub fn access_token(&self) -> Option<String> {
+        self.access_token
+            .clone()
+            .or_else(|| std::env::var("BANGUMI_ACCESS_TOKEN").ok())
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty())
+    }
+}
+
+impl Default for BangumiSettings {
+    fn default() -> Self {
+        Self {
+            base_url: default_bangumi_base_url(),
+            user_agent: default_bangumi_user_agent(),
+            page_limit: default_bangumi_page_limit(),
+            max_results: default_bangumi_max_results(),
+            access_token: None,
+            cache_ttl_hours: default_bangumi_cache_ttl_hours(),
+            cache_file: None,
+            concurrency: default_bangumi_concurrency(),
+            requests_per_second: default_bangumi_requests_per_second(),
+            max_retries: default_bangumi_max_retries(),
+            search_filter: SearchFilter::default(),
+        }
+    }
+}
+
+fn default_bangumi_base_url() -> String {
+    "https://api.bgm.tv".to_string()
+}
+
+fn default_bangumi_user_agent() -> String {
+    "smart_bangumi_qb_rule_generator/0.1.0".to_string()
+}
+
+fn default_bangumi_page_limit() -> usize {
+    20
+}
+
+fn default_bangumi_max_results() -> usize {
+    40
+}
+
+fn default_bangumi_cache_ttl_hours() -> u64 {
+    24
+}
+
+fn default_bangumi_concurrency() -> usize {
+    4
+}
+
+fn default_bangumi_requests_per_second() -> f64 {
+    5.0
+}
+
+fn default_bangumi_max_retries() -> u32 {
+    3
+}
+
+/// Bangumi搜索过滤条件
+///
+/// 同时写入搜索请求体并在本地过滤候选作品，缓存中的旧搜索结果也会按当前条件过滤。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchFilter {
+    /// 是否包含NSFW条目
+    #[serde(default)]
+    pub nsfw: bool,
+    /// 允许的放送平台（TV、WEB、OVA、剧场版等），为空时不限制。
+    /// 搜索API不支持按平台过滤，只在本地过滤；平台未知的条目保留
+    #[serde(default = "default_search_platforms")]
+    pub platforms: Vec<String>,
+    /// 条目必须带有的全部标签
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 最低评分，设置后没有评分的条目也会被排除
+    #[serde(default)]
+    pub min_rating: Option<f32>,
+}
+
+impl SearchFilter {
+    /// 把过滤条件写入搜索请求体的filter
+    pub fn apply_to_body(&self, request_body: &mut serde_json::Value) {
+        let filter = &mut request_body["filter"];
+        if !self.nsfw {
+            filter["nsfw"] = serde_json::json!(false);
+        }
+        if !self.tags.is_empty() {
+            filter["tag"] = serde_json::json!(self.tags);
+        }
+        if let Some(min_rating) = self.min_rating {
+            filter["rating"] = serde_json::json!([format!(">={}", min_rating)]);
+        }
+    }
+
+    /// 条目是否满足过滤条件
+    pub fn accepts(&self, subject: &BangumiSubject) -> bool {
+        if subject.nsfw && !self.nsfw {
+            return false;
+        }
+
+        let platform = normalize_platform(&subject.platform);
+        if !platform.is_empty()
+            && !self.platforms.is_empty()
+            && !self.platforms.iter().any(|allowed| normalize_platform(allowed) == platform)
+        {
+            return false;
+        }
+
+        // 搜索结果没有标签时无法判断，交给API过滤
+        if !subject.tags.is_empty()
+            && !self.tags.iter().all(|tag| subject.tags.iter().any(|t| t.name == *tag))
+        {
+            return false;
+        }
+
+        match self.min_rating {
+            Some(min_rating) => subject.score().is_some_and(|score| score >= min_rating),
+            None => true,
+        }
+    }
+}
+
+impl Default for SearchFilter {
+    fn default() -> Self {
+        Self {
+            nsfw: false,
+            platforms: default_search_platforms(),
+            tags: Vec::new(),
+            min_rating: None,
+        }
+    }
+}
+
+fn default_search_platforms() -> Vec<String> {
+    ["TV", "WEB", "OVA", "剧场版"].iter().map(|platform| platform.to_string()).collect()
+}
+
+/// 统一平台名的写法：忽略大小写，劇場版视为剧场版
+fn normalize_platform(platform: &str) -> String {
+    platform.trim().to_uppercase().replace('劇', "剧").replace('場', "场")
+}
+
+/// 作品匹配方式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum MatchMode {
+    /// 全部交给AI匹配
+    #[default]
+    Ai,
+    /// 只使用本地模糊匹配，不需要API Key
+    Local,
+    /// 先用本地匹配解决明显的作品，其余交给AI
+    Hybrid,
+}
+
+/// tasks.json 中的作品匹配设置
+#[derive(Debug, Deserialize, Clone)]
+pub struct MatchSettings {
+    /// 匹配方式，没有API Key时自动使用本地匹配
+    #[serde(default)]
+    pub mode: MatchMode,
+    /// 混合模式下本地评分达到该值即直接采纳，不再交给AI
+    #[serde(default = "default_prefilter_threshold")]
+    pub prefilter_threshold: f32,
+    /// 置信度高于该值的匹配直接采纳
+    #[serde(default = "default_accept_threshold")]
+    pub accept_threshold: f32,
+    /// 置信度高于该值但未达到采纳阈值的匹配进入人工复核队列
+    #[serde(default = "default_review_threshold")]
+    pub review_threshold: f32,
+    /// 复核队列文件路径
+    #[serde(default = "default_review_file")]
+    pub review_file: String,
+    /// 手动指定匹配结果的覆盖文件，默认为配置目录下的 overrides.json
+    #[serde(default)]
+    pub override_file: Option<String>,
+    /// 每个作品最多保留的候选作品数（按本地评分排序）
+    #[serde(default = "default_max_candidates")]
+    pub max_candidates: usize,
+    /// 最佳候选的本地评分达到该值且明显领先时直接采纳，不调用AI
+    #[serde(default = "default_dominant_score")]
+    pub dominant_score: f32,
+    /// 最佳候选需领先第二名的分数
+    #[serde(default = "default_dominant_margin")]
+    pub dominant_margin: f32,
+    /// 按放送日期搜索时前后的天数
+    #[serde(default = "default_air_date_window_days")]
+    pub air_date_window_days: u32,
+    /// 搜索无结果时依次扩大到的天数，0表示不限制放送日期
+    #[serde(default = "default_air_date_fallback_windows")]
+    pub air_date_fallback_windows: Vec<u32>,
+}
+
+impl MatchSettings {
+    /// 覆盖文件路径
+    pub fn override_path(&self) -> PathBuf {
+        self.override_file
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| crate::utils::config_dir().join("overrides.json"))
+    }
+
+    /// 依次尝试的放送日期范围
+    pub fn air_date_windows(&self) -> Vec<AirDateWindow> {
+        let mut windows = Vec::new();
+        for days in std::iter::once(self.air_date_window_days).chain(self.air_date_fallback_windows.iter().copied()) {
+            let window = AirDateWindow::from_days(days);
+            if !windows.contains(&window) {
+                windows.push(window);
+            }
+        }
+        windows
+    }
+}
+
+/// 搜索时使用的放送日期范围
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AirDateWindow {
+    /// 放送日期前后的天数
+    Days(u32),
+    /// 不限制放送日期
+    Unlimited,
+}
+
+impl AirDateWindow {
+    /// 0天表示不限制放送日期
+    pub fn from_days(days: u32) -> Self {
+        if days == 0 { Self::Unlimited } else { Self::Days(days) }
+    }
+}
+
+impl std::fmt::Display for AirDateWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Days(days) => write!(f, "±{}天", days),
+            Self::Unlimited => write!(f, "不限日期"),
+        }
+    }
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            mode: MatchMode::default(),
+            prefilter_threshold: default_prefilter_threshold(),
+            accept_threshold: default_accept_threshold(),
+            review_threshold: default_review_threshold(),
+            review_file: default_review_file(),
+            override_file: None,
+            max_candidates: default_max_candidates(),
+            dominant_score: default_dominant_score(),
+            dominant_margin: default_dominant_margin(),
+            air_date_window_days: default_air_date_window_days(),
+            air_date_fallback_windows: default_air_date_fallback_windows(),
+        }
+    }
+}
+
+fn default_air_date_window_days() -> u32 {
+    100
+}
+
+fn default_air_date_fallback_windows() -> Vec<u32> {
+    vec![365, 0]
+}
+
+fn default_accept_threshold() -> f32 {
+    crate::ai::object_matcher::DEFAULT_ACCEPT_THRESHOLD
+}
+
+fn default_prefilter_threshold() -> f32 {
+    0.92
+}
+
+fn default_review_threshold() -> f32 {
+    0.4
+}
+
+fn default_max_candidates() -> usize {
+    8
+}
+
+fn default_dominant_score() -> f32 {
+    0.85
+}
+
+fn default_dominant_margin() -> f32 {
+    0.25
+}
+
+fn default_review_file() -> String {
+    "review_queue.json".to_string()
+}
+
+/// tasks.json 中的AI相关设置，所有字段均可省略
+#[derive(Debug, Deserialize, Clone)]
+pub struct AiSettings {
+    /// AI花费上限（与计费单价使用相同货币），为空表示不限制
+    #[serde(default)]
+    pub budget: Option<f64>,
+    /// 超出预算后的处理方式
+    #[serde(default)]
+    pub on_budget_exceeded: BudgetAction,
+    /// 覆盖默认的计费单价
+    #[serde(default)]
+    pub pricing: Option<AiPricing>,
+    /// 同时进行的AI批次请求数
+    #[serde(default = "default_ai_concurrency")]
+    pub concurrency: usize,
+    /// 相邻两次AI请求之间的最小间隔（毫秒）
+    #[serde(default = "default_ai_request_interval_ms")]
+    pub request_interval_ms: u64,
+    /// 自定义提示词模板目录，默认为配置目录下的 prompts
+    #[serde(default)]
+    pub prompts_dir: Option<String>,
+}
+
+impl Default for AiSettings {
+    fn default() -> Self {
+        Self {
+            budget: None,
+            on_budget_exceeded: BudgetAction::default(),
+            pricing: None,
+            concurrency: default_ai_concurrency(),
+            request_interval_ms: default_ai_request_interval_ms(),
+            prompts_dir: None,
+        }
+    }
+}
+
+fn default_ai_concurrency() -> usize {
+    3
+}
+
+fn default_ai_request_interval_ms() -> u64 {
+    500
+}
+
+/// 超出AI预算后的处理方式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum BudgetAction {
+    /// 立即停止运行
+    Stop,
+    /// 停止调用AI，改用非AI处理
+    #[default]
+    Fallback,
+}
+
+/// 每百万token的计费单价
+#[derive(Debug, Deserialize, Clone)]
+pub struct AiPricing {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+    pub currency: String,
+}
+
+impl AiPricing {
+    /// 计算给定token数量的费用
+    pub fn cost(&self, input_tokens: usize, output_tokens: usize) -> f64 {
+        (input_tokens as f64 * self.input_per_million + output_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
 }
 
 impl Task {
@@ -71,12 +681,27 @@ pub struct TableInfo {
     pub table_html: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct AnimeWork {
     pub original_title: String,
     pub cleaned_title: String,
     pub air_date: Option<NaiveDate>,
     pub keywords: Vec<String>,
+    /// 标题的清理方式
+    #[serde(default)]
+    pub cleaned_by: CleaningMethod,
+}
+
+/// 作品标题的清理方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum CleaningMethod {
+    /// 未清理，使用原标题
+    #[default]
+    Raw,
+    /// 规则清理
+    Rule,
+    /// AI清理
+    Ai,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -88,20 +713,84 @@ pub struct BangumiResult {
     pub aliases: Vec<String>,
     pub air_date: Option<NaiveDate>,
     pub keywords: Vec<String>,
+    /// AI给出的匹配置信度（0-1）
+    #[serde(default)]
+    pub match_confidence: Option<f32>,
+    /// AI给出的匹配（或不匹配）理由
+    #[serde(default)]
+    pub match_reasoning: Option<String>,
+    /// 参与匹配的候选作品数量
+    #[serde(default)]
+    pub candidate_count: usize,
+    /// 需要人工复核的原因，为空表示无需复核
+    #[serde(default)]
+    pub review_notes: Vec<String>,
+    /// 标题的清理方式
+    #[serde(default)]
+    pub cleaned_by: CleaningMethod,
+    /// 匹配成功后获取的条目详情
+    #[serde(default)]
+    pub details: Option<BangumiSubjectDetail>,
+    /// 根据剧集列表确定的本篇集数范围，未获取到剧集列表时为空
+    #[serde(default)]
+    pub episode_range: Option<EpisodeRange>,
+    /// 前传链，最近的一部在前
+    #[serde(default)]
+    pub prequels: Vec<BangumiRelatedSubject>,
+    /// 找到候选作品时使用的放送日期范围
+    #[serde(default)]
+    pub air_date_window: Option<AirDateWindow>,
+    /// 条目详情infobox中的常用字段
+    #[serde(default)]
+    pub info: InfoboxInfo,
 }
 
-#[derive(Debug)]
+/// 本篇集数范围，按Bangumi剧集的 `sort` 编号（续播分割季从13等继续编号）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct EpisodeRange {
+    pub first: u32,
+    /// 最后一集，剧集列表尚未列全时为空
+    pub last: Option<u32>,
+}
+
+#[derive(Debug, Default)]
 pub struct Statistics {
     pub total_works_from_table: usize,
     pub works_with_undetermined_date: usize,
     pub works_processed_by_ai: usize,
+    pub works_cleaned_by_rule: usize,
     pub works_with_bangumi_info: usize,
     pub works_without_bangumi_info: usize,
+    pub works_with_subject_details: usize,
     pub qb_rules_generated: usize,
     pub qb_rules_failed: usize,
     pub ai_requests_count: usize,
     pub ai_input_tokens: usize,
     pub ai_output_tokens: usize,
+    pub ai_estimated_input_tokens: usize,
+    pub ai_estimated_output_tokens: usize,
+    pub ai_estimated_cost: f64,
+    pub ai_actual_cost: f64,
+    pub ai_cost_currency: String,
+    pub ai_budget: Option<f64>,
+    pub ai_budget_exceeded: bool,
+}
+
+impl Statistics {
+    /// 使用AI预算跟踪器中的用量数据更新统计
+    pub fn apply_ai_usage(&mut self, budget: &AiBudget) {
+        let usage = budget.snapshot();
+        self.ai_requests_count = usage.requests;
+        self.ai_input_tokens = usage.input_tokens;
+        self.ai_output_tokens = usage.output_tokens;
+        self.ai_estimated_input_tokens = usage.estimated.input_tokens;
+        self.ai_estimated_output_tokens = usage.estimated.output_tokens;
+        self.ai_estimated_cost = budget.estimated_cost();
+        self.ai_actual_cost = budget.spent();
+        self.ai_cost_currency = budget.pricing.currency.clone();
+        self.ai_budget = budget.limit;
+        self.ai_budget_exceeded = usage.budget_exceeded;
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -109,17 +798,67 @@ pub struct AiConfig {
     pub provider: AiProvider,
     pub model: String,
     pub api_url: String,
+    pub budget: AiBudget,
+    /// 同时进行的批次请求数上限
+    pub concurrency: usize,
+    /// 所有AI请求共享的速率限制器
+    pub rate_limiter: Arc<RateLimiter>,
+    /// 提示词模板
+    pub prompts: Arc<PromptTemplates>,
 }
 
 impl AiConfig {
     pub fn deepseek() -> Self {
+        let provider = AiProvider::DeepSeek;
         Self {
-            provider: AiProvider::DeepSeek,
+            budget: AiBudget::new(provider.default_pricing(), None, BudgetAction::default()),
+            provider,
             model: "deepseek-chat".to_string(),
             api_url: "https://api.deepseek.com/v1/chat/completions".to_string(),
+            concurrency: default_ai_concurrency(),
+            rate_limiter: Arc::new(RateLimiter::new(Duration::from_millis(
+                default_ai_request_interval_ms(),
+            ))),
+            prompts: Arc::new(PromptTemplates::default()),
         }
     }
 
+    /// 读取当前AI提供商的API Key
+    pub fn api_key(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let key = match self.provider {
+            AiProvider::DeepSeek => std::env::var("DEEPSEEK_API_KEY")
+                .map_err(|_| "未设置环境变量 DEEPSEEK_API_KEY")?,
+            // 未来支持其他AI提供商
+            // AiProvider::OpenAi => std::env::var("OPENAI_API_KEY")?,
+            // AiProvider::Claude => std::env::var("CLAUDE_API_KEY")?,
+        };
+        Ok(key)
+    }
+
+    pub fn has_api_key(&self) -> bool {
+        self.api_key().is_ok()
+    }
+
+    /// 应用tasks.json中的AI设置
+    pub fn with_settings(mut self, settings: &AiSettings) -> Self {
+        let pricing = settings
+            .pricing
+            .clone()
+            .unwrap_or_else(|| self.provider.default_pricing());
+        self.budget = AiBudget::new(pricing, settings.budget, settings.on_budget_exceeded);
+        self.concurrency = settings.concurrency.max(1);
+        self.rate_limiter = Arc::new(RateLimiter::new(Duration::from_millis(
+            settings.request_interval_ms,
+        )));
+        let prompts_dir = settings
+            .prompts_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| crate::utils::config_dir().join("prompts"));
+        self.prompts = Arc::new(PromptTemplates::load(&prompts_dir));
+        self
+    }
+
     // 预留其他AI提供商的配置
     // pub fn openai() -> Self {
     //     Self {
@@ -189,14 +928,179 @@ pub struct BangumiSubject {
     pub infobox: Vec<BangumiInfoboxItem>,
     #[serde(default)]
     pub date: Option<String>,
+    #[serde(default)]
+    pub rating: Option<BangumiRating>,
+    /// 放送平台：TV、WEB、OVA、剧场版等
+    #[serde(default)]
+    pub platform: String,
+    #[serde(default)]
+    pub nsfw: bool,
+    #[serde(default)]
+    pub tags: Vec<BangumiTag>,
+}
+
+impl BangumiSubject {
+    /// Bangumi评分，没有评分时为None
+    pub fn score(&self) -> Option<f32> {
+        self.rating.as_ref().map(|rating| rating.score).filter(|&score| score > 0.0)
+    }
 }
 
+/// Bangumi用户收藏（/v0/users/{username}/collections）
 #[derive(Debug, Deserialize)]
+pub struct BangumiCollection {
+    pub subject_id: u32,
+    /// 收藏类型：1想看，2看过，3在看，4搁置，5抛弃
+    #[serde(rename = "type", default)]
+    pub collection_type: u8,
+    #[serde(default)]
+    pub subject: Option<BangumiSubject>,
+}
+
+/// Bangumi分页响应
+#[derive(Debug, Deserialize)]
+pub struct BangumiPage<T> {
+    #[serde(default)]
+    pub total: usize,
+    #[serde(default)]
+    pub limit: usize,
+    #[serde(default)]
+    pub offset: usize,
+    pub data: Vec<T>,
+}
+
+/// Bangumi评分信息，未上榜作品的rank为0
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BangumiRating {
+    #[serde(default)]
+    pub rank: u32,
+    /// 评分人数
+    #[serde(default)]
+    pub total: u32,
+    #[serde(default)]
+    pub score: f32,
+}
+
+/// Bangumi条目详情（/v0/subjects/{id}）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BangumiSubjectDetail {
+    pub id: u32,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub name_cn: String,
+    /// 放送平台：TV、WEB、OVA、剧场版等
+    #[serde(default)]
+    pub platform: String,
+    #[serde(default)]
+    pub date: Option<String>,
+    /// 本篇话数
+    #[serde(default)]
+    pub eps: u32,
+    /// 含SP等在内的总话数
+    #[serde(default)]
+    pub total_episodes: u32,
+    #[serde(default)]
+    pub rating: Option<BangumiRating>,
+    #[serde(default)]
+    pub tags: Vec<BangumiTag>,
+    #[serde(default)]
+    pub images: Option<BangumiImages>,
+    #[serde(default)]
+    pub infobox: Vec<BangumiInfoboxItem>,
+}
+
+/// Bangumi关联条目（/v0/subjects/{id}/subjects）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BangumiRelatedSubject {
+    pub id: u32,
+    /// 条目类型，2为动画
+    #[serde(rename = "type", default)]
+    pub subject_type: u8,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub name_cn: String,
+    /// 关系：前传、续集、番外篇等
+    #[serde(default)]
+    pub relation: String,
+}
+
+/// Bangumi剧集（/v0/episodes）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BangumiEpisode {
+    pub id: u32,
+    /// 0本篇，1 SP，2 OP，3 ED
+    #[serde(rename = "type", default)]
+    pub episode_type: u8,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub name_cn: String,
+    /// 在整个系列中的编号
+    #[serde(default)]
+    pub sort: f64,
+    /// 在本条目中的编号
+    #[serde(default)]
+    pub ep: Option<f64>,
+    #[serde(default)]
+    pub airdate: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BangumiTag {
+    pub name: String,
+    #[serde(default)]
+    pub count: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BangumiImages {
+    #[serde(default)]
+    pub large: String,
+    #[serde(default)]
+    pub common: String,
+    #[serde(default)]
+    pub medium: String,
+    #[serde(default)]
+    pub small: String,
+    #[serde(default)]
+    pub grid: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BangumiInfoboxItem {
     pub key: String,
     pub value: serde_json::Value,
 }
 
+/// 从infobox解析出的常用字段
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct InfoboxInfo {
+    /// 别名、中文名、译名和英文名
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub english_names: Vec<String>,
+    #[serde(default)]
+    pub official_site: Option<String>,
+    /// 播放电视台
+    #[serde(default)]
+    pub tv_stations: Vec<String>,
+    /// 话数
+    #[serde(default)]
+    pub episode_count: Option<u32>,
+    /// 动画制作公司
+    #[serde(default)]
+    pub studios: Vec<String>,
+    /// 放送星期，如“星期日”
+    #[serde(default)]
+    pub broadcast_weekday: Option<String>,
+    /// 放送开始日期
+    #[serde(default)]
+    pub air_date: Option<NaiveDate>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct QBRule {
     #[serde(rename = "addPaused")]
@@ -229,6 +1133,9 @@ pub struct QBRule {
     pub torrent_params: TorrentParams,
     #[serde(rename = "useRegex")]
     pub use_regex: bool,
+    /// 对应的Bangumi条目，用于与已有规则合并，qBittorrent会忽略该字段
+    #[serde(rename = "bangumiId", default, skip_serializing_if = "Option::is_none")]
+    pub bangumi_id: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
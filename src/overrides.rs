@@ -0,0 +1,190 @@
+use crate::models::AnimeWork;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 覆盖文件中的一条规则
+///
+/// `title` 与原标题或清理后标题完全相同时生效，`pattern` 是匹配原标题或清理后标题的正则表达式。
+/// 两者至少填写一个。
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OverrideEntry {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// 直接使用该Bangumi ID，不再搜索和匹配
+    #[serde(default)]
+    pub bangumi_id: Option<u32>,
+    /// 跳过该作品，不生成规则
+    #[serde(default)]
+    pub skip: bool,
+    /// 追加的搜索关键词，没有Bangumi信息时也用于生成规则
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+/// 手动指定的匹配覆盖
+///
+/// 每次运行都会在搜索之前读取并应用，按文件中的顺序使用第一条匹配的规则。
+#[derive(Debug, Default)]
+pub struct Overrides {
+    entries: Vec<(OverrideEntry, Option<Regex>)>,
+}
+
+impl Overrides {
+    /// 读取覆盖文件，文件不存在时返回空覆盖
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        let entries: Vec<OverrideEntry> = serde_json::from_str(&content)
+            .map_err(|e| format!("覆盖文件 {} 格式错误: {}", path.display(), e))?;
+        let overrides = Self::new(entries)?;
+        log::info!("已读取覆盖文件 {}，共 {} 条规则", path.display(), overrides.entries.len());
+        Ok(overrides)
+    }
+
+    pub fn new(entries: Vec<OverrideEntry>) -> Result<Self, Box<dyn std::error::Error>> {
+        let entries = entries
+            .into_iter()
+            .map(|entry| {
+                if entry.title.is_none() && entry.pattern.is_none() {
+                    return Err("覆盖规则必须填写 title 或 pattern".into());
+                }
+                let regex = match &entry.pattern {
+                    Some(pattern) => {
+                        Some(Regex::new(pattern).map_err(|e| format!("覆盖规则的正则表达式 {} 无效: {}", pattern, e))?)
+                    }
+                    None => None,
+                };
+                Ok((entry, regex))
+            })
+            .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        Ok(Self { entries })
+    }
+
+    /// 查找作品适用的覆盖规则
+    pub fn find(&self, work: &AnimeWork) -> Option<&OverrideEntry> {
+        let titles = [work.original_title.trim(), work.cleaned_title.trim()];
+        self.entries
+            .iter()
+            .find(|(entry, regex)| {
+                let title_matches = entry
+                    .title
+                    .as_deref()
+                    .is_some_and(|title| titles.contains(&title.trim()));
+                let pattern_matches = regex
+                    .as_ref()
+                    .is_some_and(|regex| titles.iter().any(|title| regex.is_match(title)));
+                title_matches || pattern_matches
+            })
+            .map(|(entry, _)| entry)
+    }
+
+    /// 应用跳过和追加关键词，返回需要继续处理的作品
+    pub fn apply(&self, works: &[AnimeWork]) -> Vec<AnimeWork> {
+        works
+            .iter()
+            .filter_map(|work| {
+                let Some(entry) = self.find(work) else {
+                    return Some(work.clone());
+                };
+                if entry.skip {
+                    log::info!("覆盖文件指定跳过作品: {}", work.original_title);
+                    return None;
+                }
+
+                let mut work = work.clone();
+                for keyword in &entry.keywords {
+                    if !work.keywords.contains(keyword) {
+                        work.keywords.push(keyword.clone());
+                    }
+                }
+                Some(work)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::meta_providers::bangumi::{BangumiClient, search_bangumi_for_works};
+    use crate::models::{AiConfig, AnimeWork, MatchSettings};
+    use crate::test_support::spawn_mock_server;
+
+    #[tokio::test]
+    async fn test_override_file() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::models::{BangumiSettings, MatchMode};
+        use crate::overrides::Overrides;
+        use std::sync::{Arc, Mutex};
+
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let base_url = spawn_mock_server(move |request| {
+            recorded.lock().unwrap().push(request.to_string());
+            if request.starts_with("GET /v0/subjects/42 ") {
+                return (200, r#"{"id": 42, "name": "指定", "name_cn": "指定作品"}"#.to_string());
+            }
+            let data = if request.contains("\"额外\"") {
+                serde_json::json!([{"id": 7, "name": "额外", "name_cn": "", "date": ""}])
+            } else {
+                serde_json::json!([])
+            };
+            (200, serde_json::json!({"total": data.as_array().unwrap().len(), "limit": 20, "offset": 0, "data": data}).to_string())
+        })
+        .await;
+        let client = BangumiClient::new(&BangumiSettings { base_url, cache_ttl_hours: 0, ..BangumiSettings::default() })?;
+
+        let temp_dir = std::env::temp_dir();
+        let override_file = temp_dir.join(format!("brb_overrides_{}.json", std::process::id()));
+        let review_file = temp_dir.join(format!("brb_overrides_review_{}.json", std::process::id()));
+        std::fs::write(
+            &override_file,
+            r#"[
+                {"title": "指定【新】", "bangumi_id": 42},
+                {"pattern": "^跳过", "skip": true},
+                {"pattern": "关键词$", "keywords": ["额外"]}
+            ]"#,
+        )?;
+
+        let work = |original: &str, cleaned: &str| AnimeWork {
+            original_title: original.to_string(),
+            cleaned_title: cleaned.to_string(),
+            ..Default::default()
+        };
+        let works = vec![work("指定【新】", "指定"), work("跳过的作品", "跳过的作品"), work("补充（再）", "补充关键词")];
+        let settings = MatchSettings {
+            mode: MatchMode::Local,
+            review_file: review_file.to_string_lossy().into_owned(),
+            override_file: Some(override_file.to_string_lossy().into_owned()),
+            ..MatchSettings::default()
+        };
+
+        // 指定ID的作品不搜索，跳过的作品不出现在结果中，追加的关键词参与搜索
+        for _ in 0..2 {
+            let results = search_bangumi_for_works(&client, &works, &AiConfig::deepseek(), &settings).await?;
+            assert_eq!(results.len(), 2);
+            assert_eq!(results[0].bangumi_id, Some(42));
+            assert_eq!(results[0].chinese_name.as_deref(), Some("指定作品"));
+            assert!(results[0].match_reasoning.as_deref().unwrap().contains("覆盖文件"));
+            assert_eq!(results[1].original_title, "补充（再）");
+            assert!(results[1].keywords.contains(&"额外".to_string()));
+            assert_eq!(results[1].candidate_count, 1);
+        }
+        let requests = requests.lock().unwrap();
+        assert!(!requests.iter().any(|r| r.contains("\"指定\"") || r.contains("跳过")));
+
+        // 无效的规则给出明确错误
+        std::fs::write(&override_file, r#"[{"pattern": "(未闭合", "skip": true}]"#)?;
+        assert!(Overrides::load(&override_file).unwrap_err().to_string().contains("正则表达式"));
+        std::fs::write(&override_file, r#"[{"skip": true}]"#)?;
+        assert!(Overrides::load(&override_file).is_err());
+
+        let _ = std::fs::remove_file(&override_file);
+        let _ = std::fs::remove_file(&review_file);
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use crate::ai::object_matcher::CandidateWork;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 复核队列中每个作品保留的候选作品数量
+pub const REVIEW_CANDIDATE_LIMIT: usize = 5;
+
+/// 人工复核的决定
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewDecision {
+    /// 等待复核
+    #[default]
+    Pending,
+    /// 采纳 `bangumi_id` 指向的作品（可手动修改为其他候选作品的ID）
+    Accept,
+    /// 拒绝匹配，只生成关键字规则
+    Reject,
+}
+
+/// 复核队列中的一个作品
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReviewEntry {
+    pub original_title: String,
+    pub cleaned_title: String,
+    pub air_date: Option<NaiveDate>,
+    /// AI建议的Bangumi ID
+    pub bangumi_id: Option<u32>,
+    pub confidence: f32,
+    pub reasoning: String,
+    /// 排名靠前的候选作品
+    pub candidates: Vec<CandidateWork>,
+    #[serde(default)]
+    pub decision: ReviewDecision,
+}
+
+impl ReviewEntry {
+    /// 采纳的候选作品信息，复核时改成不在候选列表中的ID时返回None
+    pub fn accepted_candidate(&self) -> Option<&CandidateWork> {
+        let bangumi_id = self.bangumi_id?;
+        self.candidates.iter().find(|c| c.bangumi_id == bangumi_id)
+    }
+}
+
+/// 低置信度匹配的人工复核队列
+///
+/// 每次运行都会读取该文件，已做出决定的条目会在后续运行中按相同方式应用。
+#[derive(Debug, Default)]
+pub struct ReviewQueue {
+    pub entries: Vec<ReviewEntry>,
+}
+
+impl ReviewQueue {
+    /// 读取复核队列文件，文件不存在时返回空队列
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        let entries: Vec<ReviewEntry> = serde_json::from_str(&content)
+            .map_err(|e| format!("复核队列文件 {} 格式错误: {}", path.display(), e))?;
+        Ok(Self { entries })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(&self.entries)?)?;
+        Ok(())
+    }
+
+    /// 查找作品已做出的复核决定
+    pub fn decided(&self, original_title: &str) -> Option<&ReviewEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.original_title == original_title && e.decision != ReviewDecision::Pending)
+    }
+
+    /// 加入待复核的作品，替换该作品之前尚未复核的条目
+    pub fn enqueue(&mut self, entry: ReviewEntry) {
+        self.entries.retain(|e| {
+            e.original_title != entry.original_title || e.decision != ReviewDecision::Pending
+        });
+        self.entries.push(entry);
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| e.decision == ReviewDecision::Pending)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_review_queue_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::review::{ReviewDecision, ReviewEntry, ReviewQueue};
+        use crate::test_support::candidate;
+
+        let path = std::env::temp_dir().join(format!("brb_review_{}.json", std::process::id()));
+        let entry = |decision| ReviewEntry {
+            original_title: "青のミブロ 第二期「芹沢暗殺編」".to_string(),
+            cleaned_title: "青のミブロ 第二期 芹沢暗殺編".to_string(),
+            air_date: chrono::NaiveDate::from_ymd_opt(2025, 12, 20),
+            bangumi_id: Some(454630),
+            confidence: 0.55,
+            reasoning: "可能是第一季".to_string(),
+            candidates: vec![candidate(454630, "青のミブロ", None), candidate(560000, "青のミブロ 第二期", None)],
+            decision,
+        };
+
+        // 待复核条目不算已决定，重复加入时只保留最新一条
+        let mut queue = ReviewQueue::default();
+        queue.enqueue(entry(ReviewDecision::Pending));
+        queue.enqueue(entry(ReviewDecision::Pending));
+        assert_eq!(queue.entries.len(), 1);
+        assert!(queue.decided("青のミブロ 第二期「芹沢暗殺編」").is_none());
+        queue.save(&path)?;
+
+        // 模拟人工复核：改为第二个候选作品并采纳
+        let content = std::fs::read_to_string(&path)?
+            .replace("\"decision\": \"pending\"", "\"decision\": \"accept\"")
+            .replacen("\"bangumi_id\": 454630", "\"bangumi_id\": 560000", 1);
+        std::fs::write(&path, content)?;
+
+        let queue = ReviewQueue::load(&path)?;
+        let decided = queue
+            .decided("青のミブロ 第二期「芹沢暗殺編」")
+            .ok_or("复核决定应被读取")?;
+        assert_eq!(decided.decision, ReviewDecision::Accept);
+        assert_eq!(
+            decided.accepted_candidate().map(|c| c.japanese_title.as_str()),
+            Some("青のミブロ 第二期")
+        );
+        assert_eq!(queue.pending_count(), 0);
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// qBittorrent运行时记录的状态，始终沿用已有规则中的值
+const RUNTIME_FIELDS: &[&str] = &["lastMatch", "previouslyMatchedEpisodes"];
+
+/// 通常由用户在qBittorrent中调整的字段；没有上次生成的快照时沿用已有规则中的值
+const USER_FIELDS: &[&str] = &[
+    "enabled",
+    "addPaused",
+    "affectedFeeds",
+    "assignedCategory",
+    "ignoreDays",
+    "priority",
+    "savePath",
+    "smartFilter",
+    "torrentContentLayout",
+    "torrentParams",
+];
+
+/// 本工具生成的过滤条件；没有上次生成的快照时无法区分用户修改，沿用已有规则中的值
+const FILTER_FIELDS: &[&str] = &["mustContain", "mustNotContain", "episodeFilter"];
+
+/// 规则中记录的Bangumi ID，qBittorrent保存规则时会丢弃该字段
+const BANGUMI_ID_FIELD: &str = "bangumiId";
+
+/// 规则合并的结果
+#[derive(Debug, Default)]
+pub struct RuleMergeReport {
+    /// 新增的规则名称
+    pub added: Vec<String>,
+    /// 有字段更新的规则名称和更新的字段
+    pub updated: Vec<(String, Vec<String>)>,
+    /// 没有变化的规则名称
+    pub unchanged: Vec<String>,
+    /// 保留了用户修改的规则名称和保留的字段
+    pub preserved: Vec<(String, Vec<String>)>,
+    /// 按Bangumi ID匹配到的已有规则：(已有规则名称, 本次生成的规则名称)，合并后沿用已有名称
+    pub matched_by_id: Vec<(String, String)>,
+    /// 本次没有生成的已有规则名称，不写入输出，在qBittorrent中保持不变
+    pub kept: Vec<String>,
+}
+
+/// 把本次生成的规则与已有规则合并
+///
+/// 先按规则名称、再按Bangumi ID查找已有规则；已有规则中没有Bangumi ID（如从WebUI读取）时，
+/// 按 `rule_ids` 中保存的规则名称查找。运行时状态（lastMatch等）始终沿用已有值；
+/// 已有值与上次生成的快照 `base` 不同的字段视为用户修改，予以保留；
+/// 没有快照时沿用保存路径、分类等用户字段和不同的过滤条件，并报告为保留。
+/// 返回合并后的本次规则（不含 `kept` 中的已有规则）。
+pub fn merge_rules(
+    generated: &Map<String, Value>,
+    existing: &Map<String, Value>,
+    base: &Map<String, Value>,
+    rule_ids: &BTreeMap<String, u32>,
+) -> (Map<String, Value>, RuleMergeReport) {
+    let mut merged = Map::new();
+    let mut report = RuleMergeReport::default();
+    let mut consumed: Vec<&str> = Vec::new();
+
+    for (name, rule) in generated {
+        let found = existing
+            .get_key_value(name)
+            .or_else(|| {
+                let bangumi_id = rule_bangumi_id(rule)?;
+                existing.iter().find(|(existing_name, existing_rule)| {
+                    let existing_id = rule_bangumi_id(existing_rule).or_else(|| rule_ids.get(existing_name.as_str()).copied());
+                    existing_id == Some(bangumi_id) && !generated.contains_key(existing_name.as_str())
+                })
+            })
+            .filter(|(existing_name, _)| !consumed.contains(&existing_name.as_str()));
+
+        let Some((existing_name, existing_rule)) = found else {
+            report.added.push(name.clone());
+            merged.insert(name.clone(), rule.clone());
+            continue;
+        };
+        consumed.push(existing_name);
+        if existing_name != name {
+            report.matched_by_id.push((existing_name.clone(), name.clone()));
+        }
+
+        let base_rule = base.get(existing_name).or_else(|| base.get(name));
+        let (rule, changed, preserved) = merge_rule(rule, existing_rule, base_rule);
+        if changed.is_empty() {
+            report.unchanged.push(existing_name.clone());
+        } else {
+            report.updated.push((existing_name.clone(), changed));
+        }
+        if !preserved.is_empty() {
+            report.preserved.push((existing_name.clone(), preserved));
+        }
+        merged.insert(existing_name.clone(), rule);
+    }
+
+    report.kept = existing
+        .keys()
+        .filter(|name| !consumed.contains(&name.as_str()))
+        .cloned()
+        .collect();
+
+    (merged, report)
+}
+
+/// 规则中记录的Bangumi ID
+fn rule_bangumi_id(rule: &Value) -> Option<u32> {
+    rule.get(BANGUMI_ID_FIELD)?.as_u64()?.try_into().ok()
+}
+
+/// 规则名称到Bangumi ID的映射，保存后用于匹配从WebUI读取的已有规则
+pub fn rule_bangumi_ids(rules: &Map<String, Value>) -> BTreeMap<String, u32> {
+    rules
+        .iter()
+        .filter_map(|(name, rule)| Some((name.clone(), rule_bangumi_id(rule)?)))
+        .collect()
+}
+
+/// 合并单条规则，返回合并后的规则、更新的字段和保留了用户修改的字段
+fn merge_rule(generated: &Value, existing: &Value, base: Option<&Value>) -> (Value, Vec<String>, Vec<String>) {
+    let (Some(generated), Some(existing)) = (generated.as_object(), existing.as_object()) else {
+        return (generated.clone(), Vec::new(), Vec::new());
+    };
+
+    // 从已有规则出发，保留本工具不生成的字段
+    let mut merged = existing.clone();
+    let mut changed = Vec::new();
+    let mut preserved = Vec::new();
+
+    for (field, value) in generated {
+        let Some(existing_value) = existing.get(field) else {
+            if field != BANGUMI_ID_FIELD {
+                changed.push(field.clone());
+            }
+            merged.insert(field.clone(), value.clone());
+            continue;
+        };
+        if RUNTIME_FIELDS.contains(&field.as_str()) || existing_value == value {
+            continue;
+        }
+
+        let user_edited = match base.and_then(|base| base.get(field)) {
+            Some(base_value) => existing_value != base_value,
+            None => USER_FIELDS.contains(&field.as_str()) || FILTER_FIELDS.contains(&field.as_str()),
+        };
+        if user_edited {
+            preserved.push(field.clone());
+        } else {
+            changed.push(field.clone());
+            merged.insert(field.clone(), value.clone());
+        }
+    }
+
+    (Value::Object(merged), changed, preserved)
+}
+
+/// 在日志中输出规则合并的结果
+pub fn log_merge_report(report: &RuleMergeReport) {
+    for name in &report.added {
+        log::info!("新增规则: {}", name);
+    }
+    for (existing_name, name) in &report.matched_by_id {
+        log::info!("规则 {} 按Bangumi ID匹配到已有规则 {}，沿用已有名称", name, existing_name);
+    }
+    for (name, fields) in &report.updated {
+        log::info!("更新规则 {}: {}", name, fields.join(", "));
+    }
+    for (name, fields) in &report.preserved {
+        log::info!("规则 {} 保留用户修改: {}", name, fields.join(", "));
+    }
+    log::info!(
+        "规则合并: {} 个新增，{} 个更新，{} 个未变，{} 个保留了用户修改，{} 个已有规则本次未生成",
+        report.added.len(),
+        report.updated.len(),
+        report.unchanged.len(),
+        report.preserved.len(),
+        report.kept.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_merge_existing_rules() {
+        use crate::rules::q_bittorrent::merge::merge_rules;
+        use serde_json::json;
+
+        let as_map = |value: serde_json::Value| value.as_object().unwrap().clone();
+        let rule = |must_contain: &str, save_path: &str, bangumi_id: u32| {
+            json!({
+                "mustContain": must_contain, "episodeFilter": "", "savePath": save_path,
+                "lastMatch": "", "previouslyMatchedEpisodes": [], "bangumiId": bangumi_id
+            })
+        };
+
+        // 上次生成的快照和用户在qBittorrent中使用后的规则
+        let base = as_map(json!({
+            "A": rule("a-old", "/anime/A", 1),
+            "B": rule("b-old", "/anime/B", 2),
+            "Old Name C": rule("c-old", "/anime/C", 3)
+        }));
+        let mut existing = base.clone();
+        existing["A"]["lastMatch"] = json!("05 Oct 2025 16:00:00 +0000");
+        existing["A"]["previouslyMatchedEpisodes"] = json!(["S01E01"]);
+        existing["B"]["mustContain"] = json!("b-user");
+        existing["B"]["savePath"] = json!("/nas/B");
+        existing.insert("Manual".to_string(), json!({"mustContain": "manual"}));
+
+        let generated = as_map(json!({
+            "A": rule("a-new", "/anime/A", 1),
+            "B": rule("b-new", "/anime/B2", 2),
+            "New Name C": rule("c-old", "/anime/C", 3),
+            "D": rule("d", "/anime/D", 4)
+        }));
+
+        let (merged, report) = merge_rules(&generated, &existing, &base, &BTreeMap::new());
+
+        // 运行时状态保留，生成的字段更新
+        assert_eq!(merged["A"]["mustContain"], "a-new");
+        assert_eq!(merged["A"]["lastMatch"], "05 Oct 2025 16:00:00 +0000");
+        assert_eq!(merged["A"]["previouslyMatchedEpisodes"], json!(["S01E01"]));
+        // 用户修改的字段保留
+        assert_eq!(merged["B"]["mustContain"], "b-user");
+        assert_eq!(merged["B"]["savePath"], "/nas/B");
+        // 按Bangumi ID匹配时沿用已有名称
+        assert!(merged.contains_key("Old Name C"));
+        assert!(!merged.contains_key("New Name C"));
+        assert!(merged.contains_key("D"));
+        assert!(!merged.contains_key("Manual"));
+
+        assert_eq!(report.added, vec!["D"]);
+        assert_eq!(report.updated, vec![("A".to_string(), vec!["mustContain".to_string()])]);
+        assert_eq!(report.unchanged, vec!["B", "Old Name C"]);
+        assert_eq!(report.preserved, vec![("B".to_string(), vec!["mustContain".to_string(), "savePath".to_string()])]);
+        assert_eq!(report.matched_by_id, vec![("Old Name C".to_string(), "New Name C".to_string())]);
+        assert_eq!(report.kept, vec!["Manual"]);
+
+        // 没有快照时沿用保存路径等用户字段和不同的过滤条件
+        let (merged, report) = merge_rules(&generated, &existing, &serde_json::Map::new(), &BTreeMap::new());
+        assert_eq!(merged["A"]["mustContain"], "a-old");
+        assert_eq!(merged["B"]["mustContain"], "b-user");
+        assert_eq!(merged["B"]["savePath"], "/nas/B");
+        assert!(report.updated.is_empty());
+        assert_eq!(
+            report.preserved,
+            vec![
+                ("A".to_string(), vec!["mustContain".to_string()]),
+                ("B".to_string(), vec!["mustContain".to_string(), "savePath".to_string()])
+            ]
+        );
+    }
+    #[test]
+    fn test_merge_webui_rules_without_bangumi_id() {
+        use crate::rules::q_bittorrent::merge::{merge_rules, rule_bangumi_ids};
+        use serde_json::json;
+
+        let as_map = |value: serde_json::Value| value.as_object().unwrap().clone();
+        let generated = as_map(json!({
+            "New Name C": {"mustContain": "c", "savePath": "/anime/C", "bangumiId": 3},
+            "D": {"mustContain": "d", "savePath": "/anime/D", "bangumiId": 4}
+        }));
+        // 上次生成时的规则名称，用户之后在qBittorrent中改名为Old Name C
+        let rule_ids = rule_bangumi_ids(&as_map(json!({
+            "Old Name C": {"mustContain": "c", "bangumiId": 3},
+            "Manual": {"mustContain": "manual"}
+        })));
+        assert_eq!(rule_ids, BTreeMap::from([("Old Name C".to_string(), 3)]));
+
+        // 从WebUI读取的规则中没有bangumiId
+        let existing = as_map(json!({
+            "Old Name C": {"mustContain": "c", "savePath": "/nas/C", "lastMatch": "05 Oct 2025 16:00:00 +0000"},
+            "Manual": {"mustContain": "manual"}
+        }));
+
+        let (merged, report) = merge_rules(&generated, &existing, &serde_json::Map::new(), &rule_ids);
+        assert_eq!(report.matched_by_id, vec![("Old Name C".to_string(), "New Name C".to_string())]);
+        assert_eq!(merged["Old Name C"]["savePath"], "/nas/C");
+        assert_eq!(merged["Old Name C"]["lastMatch"], "05 Oct 2025 16:00:00 +0000");
+        assert_eq!(merged["Old Name C"]["bangumiId"], 3);
+        assert_eq!(report.added, vec!["D"]);
+        assert_eq!(report.kept, vec!["Manual"]);
+
+        // 没有保存的映射时无法匹配，作为新规则添加
+        let (merged, report) = merge_rules(&generated, &existing, &serde_json::Map::new(), &BTreeMap::new());
+        assert!(merged.contains_key("New Name C"));
+        assert_eq!(report.added, vec!["D", "New Name C"]);
+        assert!(report.matched_by_id.is_empty());
+    }
+}
@@ -1,4 +1,9 @@
-use crate::models::{BangumiResult, QBRule, TorrentParams, RuleGenerationResult, Task};
+pub mod merge;
+pub mod webui;
+
+use crate::models::{BangumiResult, EpisodeRange, QBRule, TorrentParams, RuleGenerationResult, Task};
+use crate::text::keywords::{chinese_numeral, contains_season, ordinal, parse_season};
+use crate::text::normalize::{dedup_by_key, match_key, name_pattern};
 
 fn sanitize_work_name(work_name: &str) -> String {
     let mut sanitized = work_name.to_string();
@@ -31,6 +36,125 @@ fn sanitize_work_name(work_name: &str) -> String {
     }
 }
 
+/// 集数延续前作编号时的qBittorrent剧集过滤，如第1季从第13集开始时为 `1x13-24;`，剧集未列全时为 `1x13-;`
+///
+/// 季数未知时按第1季；从第1集开始时不需要过滤，返回空字符串。
+pub fn episode_filter(range: Option<&EpisodeRange>, season: Option<u32>) -> String {
+    match range {
+        Some(range) if range.first > 1 => format!(
+            "{}x{}-{};",
+            season.unwrap_or(1),
+            range.first,
+            range.last.map(|last| last.to_string()).unwrap_or_default()
+        ),
+        _ => String::new(),
+    }
+}
+
+/// 集数延续前作编号时，匹配前作集数的正则表达式，如从第13集开始时匹配 ` - 05 `、`[12]`、`第3话`
+///
+/// 作为剧集过滤的后备：qBittorrent的剧集过滤只识别 `S01E13`、`1x13` 这样的写法，其他写法的前作集数由该表达式排除。
+/// 集数只在 ` - `、`[`、`【`、`第` 之后识别，避免把 `Season 2` 等标题中的数字当作集数；从第1集开始时返回None。
+pub fn earlier_episodes_pattern(range: &EpisodeRange) -> Option<String> {
+    if range.first <= 1 {
+        return None;
+    }
+    let numbers: Vec<String> = (1..range.first).map(|episode| episode.to_string()).collect();
+    Some(format!(
+        "(?:\\s-\\s?|[\\[【]|第)0*(?:{})(?:v\\d)?(?:[\\]】\\s]|话|話|集)",
+        numbers.join("|")
+    ))
+}
+
+/// 作品的季数，只取标题、中文名和别名中的季号
+///
+/// 前传链中混有剧场版、OVA和分割放送的后半，按其长度推算的季数并不可靠，因此不作推算。
+pub fn season_number(result: &BangumiResult) -> Option<u32> {
+    std::iter::once(&result.cleaned_title)
+        .chain(result.chinese_name.as_ref())
+        .chain(result.aliases.iter())
+        .find_map(|title| parse_season(title))
+        .map(|(_, season)| season)
+}
+
+/// 季号常见写法的正则表达式，如 `第2期`、`第二季`、`S2`、`2nd Season`
+pub fn season_token_pattern(season: u32) -> String {
+    format!(
+        "(第\\s?0?{n}\\s?[期季]|第{cn}[期季]|S0?{n}[^\\d]|Season\\s?0?{n}[^\\d]|{ord}\\s?Season)",
+        n = season,
+        cn = chinese_numeral(season),
+        ord = ordinal(season)
+    )
+}
+
+/// 会被名称模式误匹配的前作名称
+///
+/// 本作的某个名称是前作名称的一部分、而前作名称不包含于本作任何名称时，
+/// 前作的资源也会被匹配，需要排除；前作名称是本作名称一部分时无法排除。
+pub fn over_matching_prequel_names(result: &BangumiResult, names: &[String]) -> Vec<String> {
+    let name_keys: Vec<String> = names.iter().map(|name| match_key(name)).filter(|key| !key.is_empty()).collect();
+    let prequel_names = result
+        .prequels
+        .iter()
+        .flat_map(|prequel| [prequel.name.clone(), prequel.name_cn.clone()])
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    dedup_by_key(prequel_names)
+        .into_iter()
+        .filter(|prequel_name| {
+            let prequel_key = match_key(prequel_name);
+            name_keys.iter().any(|key| prequel_key.contains(key.as_str()))
+                && !name_keys.iter().any(|key| key.contains(prequel_key.as_str()))
+        })
+        .collect()
+}
+
+/// 排除合集、先行版和V0版本；合集按本作的第一集编号识别，如 `13-24`
+///
+/// 集数延续前作编号时排除前作的集数；`excluded_names` 中的名称（如会误匹配的前作名称）也一并排除。
+pub fn must_not_contain(range: Option<&EpisodeRange>, excluded_names: &[String]) -> String {
+    let mut batch_starts = vec![1];
+    if let Some(range) = range
+        && range.first > 1
+    {
+        batch_starts.push(range.first);
+    }
+
+    let batch_patterns: Vec<String> = batch_starts
+        .iter()
+        .map(|first| format!(".+{:02}\\-.+", first))
+        .collect();
+    let mut pattern = format!("{}|.+合集.+|.+先行.+|.+\\[V0.+|.+全集.+", batch_patterns.join("|"));
+    if let Some(earlier_episodes) = range.and_then(earlier_episodes_pattern) {
+        pattern.push_str(&format!("|.+{}.*", earlier_episodes));
+    }
+    for name in excluded_names {
+        pattern.push_str(&format!("|.+{}.+", name_pattern(name)));
+    }
+    pattern
+}
+
+/// mustContain中的名称部分
+///
+/// 续作的集数从1重新开始时，不含季号的名称必须与季号写法同时出现，避免匹配前作；
+/// 集数延续前作编号或无法确定季数时不要求季号，前者由mustNotContain中的集数区分。
+fn names_pattern(result: &BangumiResult, names: &[String]) -> String {
+    let continues_numbering = result.episode_range.is_some_and(|range| range.first > 1);
+    let season = match season_number(result) {
+        Some(season) if season > 1 && !continues_numbering => season,
+        _ => return names.iter().map(|name| name_pattern(name)).collect::<Vec<_>>().join("|"),
+    };
+
+    let (seasonal, plain): (Vec<&String>, Vec<&String>) = names.iter().partition(|name| contains_season(name));
+    let mut alternatives: Vec<String> = seasonal.iter().map(|name| name_pattern(name)).collect();
+    if !plain.is_empty() {
+        let plain_patterns: Vec<String> = plain.iter().map(|name| name_pattern(name)).collect();
+        alternatives.push(format!("(?:{}).*{}", plain_patterns.join("|"), season_token_pattern(season)));
+    }
+    alternatives.join("|")
+}
+
 pub fn generate_qb_rules(
     bangumi_results: &[BangumiResult],
     task: &Task,
@@ -65,20 +189,21 @@ pub fn generate_qb_rules(
             all_names.extend_from_slice(&result.keywords);
         }
 
-        // 去重并转义特殊字符
-        let mut unique_names: Vec<String> = all_names.into_iter().collect::<std::collections::HashSet<_>>().into_iter().collect();
+        // 按规范化后的名称去重，生成兼容宽度、分隔符号和简繁字形的模式
+        let mut unique_names = dedup_by_key(all_names);
         unique_names.sort();
-        let escaped_names: Vec<String> = unique_names.iter().map(|name| regex::escape(name)).collect();
 
-        // 构建mustContain模式
-        let name_pattern = escaped_names.join("|");
+        // 构建mustContain模式，续作按季号区分前作
+        let name_pattern = names_pattern(result, &unique_names);
         let must_contain = format!(
             ".+({}).+((1080|2160|WebRip).+(CHS|CHT|GB|BIG5|简|繁|B-Global|Baha|bilibili|CR|Sentai|x264\\sAAC|无字幕)|(CHS|CHT|GB|BIG5|简|繁|B-Global|Baha|bilibili|CR|Sentai|x264\\sAAC|无字幕).+(1080|2160|WebRip)).+",
             name_pattern
         );
 
-        // 构建mustNotContain模式
-        let must_not_contain = ".+01\\-.+|.+合集.+|.+先行.+|.+\\[V0.+|.+全集.+".to_string();
+        // 构建mustNotContain模式，其中的前作集数作为剧集过滤的后备
+        let prequel_names = over_matching_prequel_names(result, &unique_names);
+        let must_not_contain = must_not_contain(result.episode_range.as_ref(), &prequel_names);
+        let episode_filter = episode_filter(result.episode_range.as_ref(), season_number(result));
 
         // 清理作品名称中的非法字符
         let sanitized_work_name = sanitize_work_name(&work_name);
@@ -101,9 +226,9 @@ pub fn generate_qb_rules(
             ],
             assigned_category: format!("Anime/{}", season_name),
             enabled: true,
-            episode_filter: "".to_string(),
+            episode_filter,
             ignore_days: 0,
-            last_match: "05 Aug 2025 16:59:34 +0000".to_string(),
+            last_match: String::new(),
             must_contain,
             must_not_contain,
             previously_matched_episodes: vec![],
@@ -130,6 +255,7 @@ pub fn generate_qb_rules(
                 use_auto_tmm: false,
             },
             use_regex: true,
+            bangumi_id: result.bangumi_id,
         };
 
         // 使用规则名称作为键
@@ -150,4 +276,114 @@ pub fn generate_qb_rules(
         rules: serde_json::Value::Object(rules),
         failed_works,
     })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::meta_providers::bangumi::BangumiClient;
+    use crate::test_support::spawn_mock_server;
+
+    #[tokio::test]
+    async fn test_sequel_rules_from_relations() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::meta_providers::bangumi::fetch_prequel_chain;
+        use crate::models::{BangumiResult, BangumiSettings, Task};
+        use crate::rules::q_bittorrent::{generate_qb_rules, season_number, season_token_pattern};
+
+        // 600的前传是599（同名），599的前传是598（带副标题），598只有小说前传
+        let base_url = spawn_mock_server(|request| {
+            let related = if request.starts_with("GET /v0/subjects/600/subjects ") {
+                serde_json::json!([
+                    {"id": 599, "type": 2, "name": "作品", "name_cn": "作品", "relation": "前传"},
+                    {"id": 601, "type": 2, "name": "作品 劇場版", "relation": "续集"}
+                ])
+            } else if request.starts_with("GET /v0/subjects/599/subjects ") {
+                serde_json::json!([
+                    {"id": 598, "type": 2, "name": "作品 始まりの章", "name_cn": "", "relation": "前传"},
+                    {"id": 600, "type": 2, "name": "作品", "relation": "续集"}
+                ])
+            } else {
+                serde_json::json!([{"id": 700, "type": 1, "name": "作品 小说", "relation": "前传"}])
+            };
+            (200, related.to_string())
+        })
+        .await;
+        let client = BangumiClient::new(&BangumiSettings { base_url, cache_ttl_hours: 0, ..BangumiSettings::default() })?;
+
+        let prequels = fetch_prequel_chain(&client, 600).await?;
+        assert_eq!(prequels.iter().map(|p| p.id).collect::<Vec<_>>(), vec![599, 598]);
+
+        let mut result: BangumiResult = serde_json::from_str(
+            r#"{"original_title": "作品", "cleaned_title": "作品", "bangumi_id": 600, "chinese_name": "作品",
+                "aliases": ["作品 第三季"], "air_date": null, "keywords": []}"#,
+        )?;
+        result.prequels = prequels;
+        assert_eq!(season_number(&result), Some(3));
+
+        let task: Task = serde_json::from_str(r#"{"description": "test", "site": "Kansou", "root_path": "/anime"}"#)?;
+        let rules = generate_qb_rules(&[result.clone()], &task, "2025年10月")?.rules;
+        let rule = &rules["2025年10月 作品"];
+        let must_contain = regex::Regex::new(&format!("(?i){}", rule["mustContain"].as_str().unwrap()))?;
+        let must_not_contain = regex::Regex::new(&format!("(?i){}", rule["mustNotContain"].as_str().unwrap()))?;
+
+        // 不带季号的名称必须与季号同时出现
+        assert!(must_contain.is_match("[字幕组] 作品 第3期 - 01 [1080p][CHS]"));
+        assert!(must_contain.is_match("[字幕组] 作品 3rd Season - 01 [1080p][CHS]"));
+        assert!(must_contain.is_match("[字幕组] 作品 S3 - 01 [1080p][CHS]"));
+        assert!(must_contain.is_match("[字幕组] 作品 第三季 - 01 [1080p][CHS]"));
+        assert!(!must_contain.is_match("[字幕组] 作品 - 01 [1080p][CHS]"));
+        assert!(!must_contain.is_match("[字幕组] 作品 S13 - 01 [1080p][CHS]"));
+
+        // 包含本作名称的前作名称被排除
+        assert!(must_not_contain.is_match("[字幕组] 作品 始まりの章 第3期 [1080p][CHS]"));
+        assert!(!must_not_contain.is_match("[字幕组] 作品 第3期 - 01 [1080p][CHS]"));
+        assert!(regex::Regex::new(&season_token_pattern(2))?.is_match("作品 2nd Season"));
+
+        // 标题中没有季号时不按前传数量推算，也不要求季号
+        let mut unnumbered = result.clone();
+        unnumbered.aliases.clear();
+        assert_eq!(season_number(&unnumbered), None);
+        let rules = generate_qb_rules(&[unnumbered], &task, "2025年10月")?.rules;
+        let must_contain = regex::Regex::new(rules["2025年10月 作品"]["mustContain"].as_str().unwrap())?;
+        assert!(must_contain.is_match("[字幕组] 作品 - 01 [1080p][CHS]"));
+
+        // 集数延续前作编号时由集数区分，不要求季号，并按剧集列表设置qBittorrent的剧集过滤
+        assert_eq!(rules["2025年10月 作品"]["episodeFilter"], "");
+        result.episode_range = Some(crate::models::EpisodeRange { first: 25, last: Some(36) });
+        let rules = generate_qb_rules(&[result], &task, "2025年10月")?.rules;
+        let must_contain = regex::Regex::new(rules["2025年10月 作品"]["mustContain"].as_str().unwrap())?;
+        assert!(must_contain.is_match("[字幕组] 作品 - 25 [1080p][CHS]"));
+        assert_eq!(rules["2025年10月 作品"]["episodeFilter"], "3x25-36;");
+        let must_not_contain = regex::Regex::new(rules["2025年10月 作品"]["mustNotContain"].as_str().unwrap())?;
+        assert!(must_not_contain.is_match("[字幕组] 作品 - 24 [1080p][CHS]"));
+        assert!(!must_not_contain.is_match("[字幕组] 作品 - 25 [1080p][CHS]"));
+        Ok(())
+    }
+    #[test]
+    fn test_episode_exclusion_patterns() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::models::EpisodeRange;
+        use crate::rules::q_bittorrent::{earlier_episodes_pattern, episode_filter, must_not_contain};
+
+        let range = EpisodeRange { first: 13, last: Some(24) };
+        let open_range = EpisodeRange { first: 13, last: None };
+
+        // 剧集过滤使用qBittorrent的 `季x起-止;` 写法，季数未知时按第1季
+        assert_eq!(episode_filter(Some(&range), None), "1x13-24;");
+        assert_eq!(episode_filter(Some(&open_range), Some(2)), "2x13-;");
+        assert_eq!(episode_filter(Some(&EpisodeRange { first: 1, last: Some(12) }), None), "");
+        assert_eq!(episode_filter(None, Some(2)), "");
+
+        // 合集排除同时覆盖从01和从13开始的合集，前作的集数也被排除
+        let exclusion = regex::Regex::new(&must_not_contain(Some(&range), &[]))?;
+        assert!(exclusion.is_match("[字幕组] 作品 [13-24][1080p]"));
+        assert!(exclusion.is_match("[字幕组] 作品 [01-24][1080p]"));
+        assert!(exclusion.is_match("[字幕组] 作品 - 05 [1080p]"));
+        assert!(exclusion.is_match("[字幕组] 作品 [12v2][1080p]"));
+        assert!(exclusion.is_match("[字幕组] 作品 第3话 [1080p]"));
+        assert!(!exclusion.is_match("[字幕组] 作品 [14][1080p]"));
+        assert!(!exclusion.is_match("[字幕组] 作品 - 13 [1080p]"));
+        assert!(!exclusion.is_match("[字幕组] 作品 Season 2 - 13 [1080p]"));
+        assert!(earlier_episodes_pattern(&EpisodeRange { first: 1, last: Some(12) }).is_none());
+        assert_eq!(must_not_contain(None, &[]), ".+01\\-.+|.+合集.+|.+先行.+|.+\\[V0.+|.+全集.+");
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+use crate::models::QBittorrentSettings;
+
+/// qBittorrent WebUI API客户端
+///
+/// 登录后保存会话Cookie（SID），之后的请求都带上该Cookie和Referer头，以通过WebUI的CSRF检查。
+#[derive(Debug, Clone)]
+pub struct QBWebUiClient {
+    http: reqwest::Client,
+    base_url: String,
+    sid: Option<String>,
+}
+
+/// 推送规则的结果
+#[derive(Debug, Default)]
+pub struct QBPushReport {
+    /// 新建（试运行时为将要新建）的规则名称
+    pub created: Vec<String>,
+    /// 覆盖（试运行时为将要覆盖）的已有规则名称
+    pub updated: Vec<String>,
+    /// 推送失败的规则名称和原因
+    pub failed: Vec<(String, String)>,
+}
+
+impl QBWebUiClient {
+    pub fn new(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()?;
+        Ok(Self {
+            http,
+            base_url: url.trim_end_matches('/').to_string(),
+            sid: None,
+        })
+    }
+
+    /// 按配置创建客户端并登录；没有配置用户名时假定WebUI对本机免登录
+    pub async fn connect(settings: &QBittorrentSettings) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut client = Self::new(&settings.url)?;
+        if let Some(username) = &settings.username {
+            client.login(username, &settings.password().unwrap_or_default()).await?;
+        }
+        Ok(client)
+    }
+
+    /// 登录WebUI，成功时响应为 `Ok.` 并通过Set-Cookie返回SID
+    pub async fn login(&mut self, username: &str, password: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let response = self
+            .request(reqwest::Method::POST, "/api/v2/auth/login")
+            .form(&[("username", username), ("password", password)])
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::FORBIDDEN {
+            return Err("qBittorrent WebUI拒绝登录：登录失败次数过多，IP已被暂时封禁".into());
+        }
+        let sid = response
+            .headers()
+            .get_all(reqwest::header::SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(|cookie| cookie.split(';').next()?.trim().strip_prefix("SID=").map(|sid| sid.to_string()));
+        let body = response.text().await.unwrap_or_default();
+
+        if !status.is_success() || body.trim() != "Ok." {
+            return Err(format!("qBittorrent WebUI登录失败，状态码: {} {}", status, body.trim()).trim_end().into());
+        }
+        self.sid = sid;
+        Ok(())
+    }
+
+    /// 读取现有的全部RSS下载规则，键为规则名称
+    pub async fn rules(&self) -> Result<serde_json::Map<String, serde_json::Value>, Box<dyn std::error::Error>> {
+        let path = "/api/v2/rss/rules";
+        let response = self.request(reqwest::Method::GET, path).send().await?;
+        let response = check_response(path, response).await?;
+        Ok(response.json().await?)
+    }
+
+    /// 创建或覆盖一条RSS下载规则
+    pub async fn set_rule(&self, name: &str, rule: &serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
+        let path = "/api/v2/rss/setRule";
+        let response = self
+            .request(reqwest::Method::POST, path)
+            .form(&[("ruleName", name), ("ruleDef", &rule.to_string())])
+            .send()
+            .await?;
+        check_response(path, response).await?;
+        Ok(())
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let mut request = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path))
+            .header(reqwest::header::REFERER, &self.base_url);
+        if let Some(sid) = &self.sid {
+            request = request.header(reqwest::header::COOKIE, format!("SID={}", sid));
+        }
+        request
+    }
+}
+
+async fn check_response(
+    path: &str,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+    let status = response.status();
+    if status == reqwest::StatusCode::FORBIDDEN {
+        return Err(format!("qBittorrent WebUI请求 {} 被拒绝，请检查用户名和密码", path).into());
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("qBittorrent WebUI请求 {} 失败，状态码: {} {}", path, status, body.trim()).trim_end().into());
+    }
+    Ok(response)
+}
+
+/// 把生成的规则逐条推送到qBittorrent，同名规则会被覆盖
+///
+/// `existing_names` 为WebUI中已有的规则名称，用于区分新建和覆盖；试运行时只列出将要新建和覆盖的规则。
+pub async fn push_rules(
+    client: &QBWebUiClient,
+    rules: &serde_json::Value,
+    existing_names: &[String],
+    dry_run: bool,
+) -> Result<QBPushReport, Box<dyn std::error::Error>> {
+    let rules = rules.as_object().ok_or("规则必须是以规则名称为键的JSON对象")?;
+
+    let mut report = QBPushReport::default();
+    for (name, rule) in rules {
+        let exists = existing_names.contains(name);
+        let action = if exists { "覆盖" } else { "新建" };
+
+        if dry_run {
+            log::info!("[试运行] 将{}规则: {}", action, name);
+        } else if let Err(e) = client.set_rule(name, rule).await {
+            log::warn!("推送规则 {} 失败: {}", name, e);
+            report.failed.push((name.clone(), e.to_string()));
+            continue;
+        } else {
+            log::info!("已{}规则: {}", action, name);
+        }
+
+        if exists {
+            report.updated.push(name.clone());
+        } else {
+            report.created.push(name.clone());
+        }
+    }
+
+    log::info!(
+        "规则推送{}: {} 个{}，{} 个{}，{} 个失败",
+        if dry_run { "（试运行）" } else { "" },
+        report.created.len(),
+        if dry_run { "待新建" } else { "已新建" },
+        report.updated.len(),
+        if dry_run { "待覆盖" } else { "已覆盖" },
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::spawn_mock_server_with_headers;
+
+    #[tokio::test]
+    async fn test_qb_webui_push() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::rules::q_bittorrent::webui::{QBWebUiClient, push_rules};
+        use std::sync::{Arc, Mutex};
+
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        // qBittorrent WebUI通过Cookie保持登录会话
+        let session_cookie = "Set-Cookie: SID=mock-session; HttpOnly; path=/";
+        let base_url = spawn_mock_server_with_headers(&[session_cookie], move |request| {
+            recorded.lock().unwrap().push(request.to_string());
+            let logged_in = request.contains("SID=mock-session");
+            if request.starts_with("POST /api/v2/auth/login ") {
+                let ok = request.contains("username=admin&password=secret");
+                (200, if ok { "Ok." } else { "Fails." }.to_string())
+            } else if !logged_in {
+                (403, "Forbidden".to_string())
+            } else if request.starts_with("GET /api/v2/rss/rules ") {
+                (200, r#"{"Old Rule": {"enabled": true}}"#.to_string())
+            } else if request.starts_with("POST /api/v2/rss/setRule ") {
+                if request.contains("ruleName=Broken") { (409, "Conflict".to_string()) } else { (200, String::new()) }
+            } else {
+                (404, String::new())
+            }
+        })
+        .await;
+
+        // 密码错误时登录失败
+        let mut client = QBWebUiClient::new(&base_url)?;
+        assert!(client.login("admin", "wrong").await.is_err());
+        // 未登录时读取规则被拒绝
+        assert!(client.rules().await.is_err());
+
+        client.login("admin", "secret").await?;
+        let rules = serde_json::json!({
+            "Old Rule": {"enabled": true, "mustContain": "a"},
+            "New Rule": {"enabled": true, "mustContain": "b"},
+            "Broken": {"enabled": true}
+        });
+
+        let existing_names: Vec<String> = client.rules().await?.keys().cloned().collect();
+        assert_eq!(existing_names, vec!["Old Rule"]);
+
+        // 试运行不发送任何请求
+        requests.lock().unwrap().clear();
+        let report = push_rules(&client, &rules, &existing_names, true).await?;
+        assert_eq!(report.created, vec!["Broken", "New Rule"]);
+        assert_eq!(report.updated, vec!["Old Rule"]);
+        assert!(requests.lock().unwrap().is_empty());
+
+        // 按传入的已有规则名称区分新建和覆盖，不再读取现有规则
+        let report = push_rules(&client, &rules, &existing_names, false).await?;
+        assert_eq!(report.created, vec!["New Rule"]);
+        assert_eq!(report.updated, vec!["Old Rule"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "Broken");
+        let requests = requests.lock().unwrap();
+        assert!(!requests.iter().any(|r| r.starts_with("GET /api/v2/rss/rules ")));
+        let set_rule = requests.iter().find(|r| r.contains("ruleName=New+Rule")).unwrap();
+        assert!(set_rule.contains("ruleDef=%7B%22enabled%22%3Atrue%2C%22mustContain%22%3A%22b%22%7D"));
+        assert!(set_rule.to_lowercase().contains(&format!("referer: {}", base_url)));
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use crate::meta_providers::bangumi::BangumiClient;
+use crate::meta_providers::bangumi::collection::collection_results;
+use crate::models::{Statistics, Task};
+
+/// 从Bangumi用户收藏生成规则，不抓取季度表格，也不需要搜索和匹配
+pub async fn process_bangumi_collection(task: &Task) -> Result<(), Box<dyn std::error::Error>> {
+    let bangumi = BangumiClient::new(&task.bangumi)?;
+    let username = match &task.collection.username {
+        Some(username) => username.clone(),
+        None if bangumi.is_authenticated() => bangumi.verify_token().await?,
+        None => return Err("读取Bangumi收藏需要在 collection.username 中指定用户名，或配置访问令牌".into()),
+    };
+    log::info!("读取Bangumi用户 {} 的收藏: {:?}", username, task.collection.statuses);
+
+    let bangumi_results = collection_results(&bangumi, &username, &task.collection.statuses).await?;
+    if bangumi_results.is_empty() {
+        log::warn!("收藏中没有符合条件的动画");
+        return Ok(());
+    }
+
+    let stats = Statistics {
+        total_works_from_table: bangumi_results.len(),
+        ..Statistics::default()
+    };
+    super::write_rules_and_report(task, &bangumi_results, &task.collection.season_name, stats).await?;
+    super::update_collection_status(task, &bangumi, &bangumi_results).await;
+    Ok(())
+}
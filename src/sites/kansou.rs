@@ -1,5 +1,7 @@
-use crate::models::{AnimeWork, TableInfo, Task};
-use crate::utils::{extract_season_name_from_table_title, cache_results};
+use crate::ai::object_matcher::fuzzy::title_similarity;
+use crate::models::{AnimeWork, CleaningMethod, MatchMode, Statistics, TableInfo, Task};
+use crate::text::keywords::rule_based_work;
+use crate::utils::extract_season_name_from_table_title;
 use scraper::{Html, Selector};
 
 pub async fn process_kansou_site(task: &Task) -> Result<(), Box<dyn std::error::Error>> {
@@ -39,10 +41,25 @@ pub async fn process_kansou_site(task: &Task) -> Result<(), Box<dyn std::error::
     let tables = extract_tables_with_titles(&html_content)?;
     log::info!("找到 {} 个表格", tables.len());
 
-    // 使用AI API智能匹配表格并处理作品
-    let ai_config = crate::models::AiConfig::deepseek();
-    let (matched_table, _processed_works, mut stats) =
-        crate::ai::deepseek::match_and_process_with_ai(&task.description, &tables, &ai_config).await?;
+    // 没有API Key时改用本地匹配，不调用AI
+    let ai_config = crate::models::AiConfig::deepseek().with_settings(&task.ai);
+    let bangumi = crate::meta_providers::bangumi::BangumiClient::new(&task.bangumi)?;
+    if bangumi.is_authenticated() {
+        let username = bangumi.verify_token().await?;
+        log::info!("已使用Bangumi访问令牌登录: {}", username);
+    }
+    let mut matching = task.matching.clone();
+    if matching.mode != MatchMode::Local && !ai_config.has_api_key() {
+        log::warn!("未设置AI API Key，改用本地匹配模式");
+        matching.mode = MatchMode::Local;
+    }
+
+    // 使用AI API智能匹配表格并处理作品，本地模式下按规则选择表格
+    let (matched_table, _processed_works, mut stats) = if matching.mode == MatchMode::Local {
+        process_tables_locally(&task.description, &tables)?
+    } else {
+        crate::ai::deepseek::match_and_process_with_ai(&task.description, &tables, &ai_config).await?
+    };
 
     if let Some((table, works)) = matched_table {
         log::info!("匹配到的表格标题: {}", table.title);
@@ -50,31 +67,17 @@ pub async fn process_kansou_site(task: &Task) -> Result<(), Box<dyn std::error::
         log::info!("提取到 {} 个作品", works.len());
 
         // 搜索Bangumi API
-        let bangumi_results = crate::meta_providers::bangumi::search_bangumi_for_works(&works).await?;
-
-        // 统计Bangumi搜索结果
-        stats.works_with_bangumi_info = bangumi_results
-            .iter()
-            .filter(|r| r.bangumi_id.is_some())
-            .count();
-        stats.works_without_bangumi_info = bangumi_results.len() - stats.works_with_bangumi_info;
+        let bangumi_results =
+            crate::meta_providers::bangumi::search_bangumi_for_works(&bangumi, &works, &ai_config, &matching)
+                .await?;
 
-        // 缓存结果
-        cache_results(&bangumi_results)?;
+        // 汇总整个运行期间的AI用量
+        stats.apply_ai_usage(&ai_config.budget);
 
-        // 从表格标题中提取季节信息
+        // 从表格标题中提取季节信息，生成规则和统计报告
         let season_name = extract_season_name_from_table_title(&table.title);
-
-        // 生成qBittorrent规则
-        let rule_result = crate::rules::q_bittorrent::generate_qb_rules(&bangumi_results, task, &season_name)?;
-        let rules_file = "qb_download_rules.json";
-        std::fs::write(rules_file, serde_json::to_string_pretty(&rule_result.rules)?)?;
-        stats.qb_rules_generated = rule_result.rules.as_object().unwrap().len();
-        stats.qb_rules_failed = rule_result.failed_works.len();
-        log::info!("qBittorrent规则已生成到: {}", rules_file);
-
-        // 生成统计报告
-        crate::utils::generate_statistics_report(&stats, &bangumi_results, &rule_result.failed_works);
+        super::write_rules_and_report(task, &bangumi_results, &season_name, stats).await?;
+        super::update_collection_status(task, &bangumi, &bangumi_results).await;
     } else {
         log::warn!("未找到匹配的表格");
     }
@@ -82,6 +85,59 @@ pub async fn process_kansou_site(task: &Task) -> Result<(), Box<dyn std::error::
     Ok(())
 }
 
+/// 不使用AI的表格处理：按季节和标题相似度选择表格，按规则清理标题和生成关键字
+#[allow(clippy::type_complexity)]
+pub fn process_tables_locally<'a>(
+    description: &str,
+    tables: &'a [TableInfo],
+) -> Result<(Option<(&'a TableInfo, Vec<AnimeWork>)>, Vec<AnimeWork>, Statistics), Box<dyn std::error::Error>> {
+    let mut stats = Statistics::default();
+
+    let Some(table_index) = select_table_locally(description, tables) else {
+        return Ok((None, Vec::new(), stats));
+    };
+    let matched_table = &tables[table_index];
+    log::info!("选择的表格标题: {}", matched_table.title);
+
+    let (works, undetermined_date_count) = parse_table_works(&matched_table.table_html)?;
+    let works: Vec<AnimeWork> = works.iter().map(rule_based_work).collect();
+    stats.total_works_from_table = works.len();
+    stats.works_cleaned_by_rule = works.len();
+    stats.works_with_undetermined_date = undetermined_date_count;
+    log::info!("从表格中解析出 {} 个作品", works.len());
+    if undetermined_date_count > 0 {
+        log::info!("过滤掉 {} 个日期未定的作品", undetermined_date_count);
+    }
+
+    Ok((Some((matched_table, works.clone())), works, stats))
+}
+
+/// 根据任务描述选择表格：优先选季节相同的表格，否则选标题最相似的表格
+pub fn select_table_locally(description: &str, tables: &[TableInfo]) -> Option<usize> {
+    if tables.is_empty() {
+        return None;
+    }
+
+    // 只有标题中带年份时季节提取才可靠，否则会退回到当前季节
+    let has_year = regex::Regex::new(r"\d{4}年").unwrap();
+    if has_year.is_match(description) {
+        let season = extract_season_name_from_table_title(description);
+        if let Some(index) = tables.iter().position(|table| {
+            has_year.is_match(&table.title) && extract_season_name_from_table_title(&table.title) == season
+        }) {
+            return Some(index);
+        }
+    }
+
+    tables
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| {
+            title_similarity(description, &a.title).total_cmp(&title_similarity(description, &b.title))
+        })
+        .map(|(index, _)| index)
+}
+
 pub fn extract_tables_with_titles(html: &str) -> Result<Vec<TableInfo>, Box<dyn std::error::Error>> {
     let document = Html::parse_document(html);
     let table_selector = Selector::parse("table").unwrap();
@@ -198,6 +254,7 @@ pub fn parse_table_works(table_html: &str) -> Result<(Vec<AnimeWork>, usize), Bo
                         cleaned_title: title_cell, // 暂时使用原标题，后面会清理
                         air_date,
                         keywords: Vec::new(),
+                        cleaned_by: CleaningMethod::Raw,
                     });
                 }
             }
@@ -1 +1,193 @@
-pub mod kansou;
\ No newline at end of file
+pub mod bangumi_collection;
+pub mod kansou;
+
+use crate::meta_providers::bangumi::BangumiClient;
+use crate::meta_providers::bangumi::collection::update_collections;
+use crate::models::{BangumiResult, Statistics, Task};
+use crate::rules::q_bittorrent::merge::{log_merge_report, merge_rules, rule_bangumi_ids};
+use crate::rules::q_bittorrent::webui::{QBWebUiClient, push_rules};
+use crate::utils::cache_results;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+/// 生成的规则文件
+const RULES_FILE: &str = "qb_download_rules.json";
+/// 上次生成、未经合并的规则快照，用于识别用户修改过的字段
+const GENERATED_RULES_FILE: &str = "qb_download_rules.generated.json";
+/// 规则名称到Bangumi ID的映射，qBittorrent保存规则时会丢弃bangumiId，合并时按此匹配
+const RULE_IDS_FILE: &str = "qb_download_rules.ids.json";
+
+/// 按配置把已匹配的作品加入Bangumi收藏，失败时只记录警告
+pub async fn update_collection_status(task: &Task, client: &BangumiClient, bangumi_results: &[BangumiResult]) {
+    if !task.collection_update.enabled {
+        return;
+    }
+    if let Err(e) = update_collections(client, bangumi_results, &task.collection_update).await {
+        log::warn!("更新Bangumi收藏失败: {}", e);
+    }
+}
+
+/// 按配置连接qBittorrent WebUI，未启用推送或连接失败时返回None
+async fn connect_qb(task: &Task) -> Option<QBWebUiClient> {
+    if !task.qbittorrent.enabled {
+        return None;
+    }
+    match QBWebUiClient::connect(&task.qbittorrent).await {
+        Ok(client) => Some(client),
+        Err(e) => {
+            log::warn!("连接qBittorrent WebUI失败: {}", e);
+            None
+        }
+    }
+}
+
+/// 从WebUI读取已有规则，失败时只记录警告
+async fn read_webui_rules(client: &QBWebUiClient) -> Option<Map<String, Value>> {
+    match client.rules().await {
+        Ok(rules) => Some(rules),
+        Err(e) => {
+            log::warn!("从qBittorrent读取已有规则失败: {}", e);
+            None
+        }
+    }
+}
+
+/// 把生成的规则推送到qBittorrent WebUI，失败时只记录警告
+async fn push_qb_rules(task: &Task, client: &QBWebUiClient, rules: &Value, existing_names: &[String]) {
+    let settings = &task.qbittorrent;
+    let rule_count = rules.as_object().map_or(0, |rules| rules.len());
+    let prompt = format!("将 {} 条规则推送到 {}，是否继续？", rule_count, settings.url);
+    if !settings.dry_run && settings.confirm && !confirm(prompt).await {
+        log::info!("已取消推送规则到qBittorrent");
+        return;
+    }
+
+    if let Err(e) = push_rules(client, rules, existing_names, settings.dry_run).await {
+        log::warn!("推送规则到qBittorrent失败: {}", e);
+    }
+}
+
+/// 在终端询问是否继续，只有输入y或yes时返回true；在阻塞线程中读取输入，不占用异步运行时
+async fn confirm(prompt: String) -> bool {
+    tokio::task::spawn_blocking(move || {
+        print!("{} [y/N] ", prompt);
+        let _ = std::io::stdout().flush();
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).is_err() {
+            return false;
+        }
+        matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    })
+    .await
+    .unwrap_or(false)
+}
+
+/// 读取规则文件，文件不存在时返回空规则
+fn read_rules_file(path: &Path) -> Result<Map<String, Value>, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(Map::new());
+    }
+    let content = std::fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(|e| format!("无法解析规则文件 {}: {}", path.display(), e).into())
+}
+
+/// 读取保存的规则名称到Bangumi ID的映射，文件不存在时返回空映射
+fn read_rule_ids(path: &Path) -> Result<BTreeMap<String, u32>, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = std::fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(|e| format!("无法解析规则ID文件 {}: {}", path.display(), e).into())
+}
+
+/// 读取已有规则：优先使用从WebUI读取的规则，没有时从配置的导出文件读取
+///
+/// 两者都没有时返回None，不进行合并。
+fn load_existing_rules(
+    task: &Task,
+    webui_rules: Option<&Map<String, Value>>,
+) -> Result<Option<Map<String, Value>>, Box<dyn std::error::Error>> {
+    if let Some(rules) = webui_rules {
+        return Ok(Some(rules.clone()));
+    }
+
+    match &task.qbittorrent.existing_rules_file {
+        Some(path) => Ok(Some(read_rules_file(Path::new(path))?)),
+        None => Ok(None),
+    }
+}
+
+/// 缓存匹配结果，生成qBittorrent规则文件并输出统计报告
+///
+/// 启用推送时只连接一次WebUI并只读取一次已有规则，用于合并和推送本次生成（已与已有规则合并）的规则。
+pub async fn write_rules_and_report(
+    task: &Task,
+    bangumi_results: &[BangumiResult],
+    season_name: &str,
+    mut stats: Statistics,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // 统计Bangumi搜索结果
+    stats.works_with_bangumi_info = bangumi_results
+        .iter()
+        .filter(|r| r.bangumi_id.is_some())
+        .count();
+    stats.works_without_bangumi_info = bangumi_results.len() - stats.works_with_bangumi_info;
+    stats.works_with_subject_details = bangumi_results
+        .iter()
+        .filter(|r| r.details.is_some())
+        .count();
+
+    // 缓存结果
+    cache_results(bangumi_results)?;
+
+    // 生成qBittorrent规则
+    let rule_result = crate::rules::q_bittorrent::generate_qb_rules(bangumi_results, task, season_name)?;
+    let generated = rule_result.rules.as_object().cloned().unwrap_or_default();
+
+    // 与已有规则合并，保留lastMatch等运行时状态和用户修改；本次没有生成的已有规则不写入输出
+    let qb_client = connect_qb(task).await;
+    let webui_rules = match &qb_client {
+        Some(client) => read_webui_rules(client).await,
+        None => None,
+    };
+    let existing = if task.qbittorrent.merge_existing {
+        let existing = load_existing_rules(task, webui_rules.as_ref())?;
+        if existing.is_none() {
+            log::warn!("未连接qBittorrent，也未配置existing_rules_file，跳过与已有规则的合并");
+        }
+        existing
+    } else {
+        None
+    };
+    let mut rule_ids = read_rule_ids(Path::new(RULE_IDS_FILE))?;
+    let rules = match existing {
+        Some(existing) => {
+            let base = read_rules_file(Path::new(GENERATED_RULES_FILE))?;
+            let (merged, report) = merge_rules(&generated, &existing, &base, &rule_ids);
+            log_merge_report(&report);
+            // 只保留仍然存在的已有规则的映射
+            rule_ids.retain(|name, _| existing.contains_key(name));
+            merged
+        }
+        None => generated.clone(),
+    };
+    rule_ids.extend(rule_bangumi_ids(&rules));
+
+    std::fs::write(RULES_FILE, serde_json::to_string_pretty(&rules)?)?;
+    std::fs::write(GENERATED_RULES_FILE, serde_json::to_string_pretty(&generated)?)?;
+    std::fs::write(RULE_IDS_FILE, serde_json::to_string_pretty(&rule_ids)?)?;
+    stats.qb_rules_generated = generated.len();
+    stats.qb_rules_failed = rule_result.failed_works.len();
+    log::info!("qBittorrent规则已生成到: {}", RULES_FILE);
+
+    // 生成统计报告
+    crate::utils::generate_statistics_report(&stats, bangumi_results, &rule_result.failed_works);
+
+    if let Some(client) = &qb_client {
+        let existing_names: Vec<String> = webui_rules.map(|rules| rules.keys().cloned().collect()).unwrap_or_default();
+        push_qb_rules(task, client, &Value::Object(rules), &existing_names).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! 测试共用的模拟服务和测试数据
+
+use crate::ai::object_matcher::CandidateWork;
+
+/// 启动本地HTTP服务模拟外部API，按完整请求文本返回（状态码, JSON）
+pub async fn spawn_mock_server<F>(handler: F) -> String
+where
+    F: Fn(&str) -> (u16, String) + Send + Sync + 'static,
+{
+    spawn_mock_server_with_headers(&[], handler).await
+}
+
+/// 同spawn_mock_server，每个响应额外带上给定的响应头
+pub async fn spawn_mock_server_with_headers<F>(headers: &[&str], handler: F) -> String
+where
+    F: Fn(&str) -> (u16, String) + Send + Sync + 'static,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let handler = std::sync::Arc::new(handler);
+    let extra_headers: String = headers.iter().map(|header| format!("{}\r\n", header)).collect();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let handler = handler.clone();
+            let extra_headers = extra_headers.clone();
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                loop {
+                    let n = stream.read(&mut buffer).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..n]);
+                    let text = String::from_utf8_lossy(&request);
+                    if let Some(header_end) = text.find("\r\n\r\n") {
+                        let content_length = text[..header_end]
+                            .lines()
+                            .find_map(|line| {
+                                let line = line.to_ascii_lowercase();
+                                line.strip_prefix("content-length:").and_then(|v| v.trim().parse::<usize>().ok())
+                            })
+                            .unwrap_or(0);
+                        if request.len() >= header_end + 4 + content_length {
+                            break;
+                        }
+                    }
+                }
+
+                let (status, body) = handler(&String::from_utf8_lossy(&request));
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    extra_headers,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    format!("http://{}", address)
+}
+
+/// 候选作品测试数据，未指定的字段为空
+pub fn candidate(id: u32, title: &str, air_date: Option<&str>) -> CandidateWork {
+    CandidateWork {
+        bangumi_id: id,
+        japanese_title: title.to_string(),
+        chinese_title: String::new(),
+        aliases: vec![],
+        air_date: air_date.map(|date| date.to_string()),
+        score: None,
+        rank: None,
+        info: Default::default(),
+    }
+}
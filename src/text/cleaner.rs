@@ -0,0 +1,111 @@
+//! Deterministic title pre-cleaning
+//!
+//! Season tables decorate titles with a small set of recurring notes (dub
+//! versions, re-broadcasts, streaming notices, `※` remarks). These are removed
+//! by rule; season numbers and `「」`/`『』` subtitles are kept. Titles that
+//! still carry unrecognised brackets afterwards are left for the AI.
+
+use super::keywords::{contains_season, strip_broadcast_markers};
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// 需要确认内容的括号，「」和『』中的副标题总是保留
+const AMBIGUOUS_BRACKETS: &[(char, char)] = &[
+    ('【', '】'),
+    ('[', ']'),
+    ('［', '］'),
+    ('（', '）'),
+    ('(', ')'),
+    ('〈', '〉'),
+];
+
+/// 已知的装饰性说明
+fn noise_regexes() -> &'static [Regex] {
+    static REGEXES: OnceLock<Vec<Regex>> = OnceLock::new();
+    REGEXES.get_or_init(|| {
+        [
+            // 括号内的配信/放送说明，如【先行配信】、（地上波放送）
+            r"[【\[［（(〈][^【】\[\]［］（）()〈〉]*(?:字幕版|先行|最速|独占|配信|地上波|放送|ノーカット|新番組|一挙)[^【】\[\]［］（）()〈〉]*[】\]］）)〉]",
+            // 新作标记，如【新】、[NEW]
+            r"(?i)[【\[［（(〈](?:新|新作|new)[】\]］）)〉]",
+            // 行首行尾的装饰符号
+            r"^[★☆◆◇●○■□▼▽※\s]+|[★☆◆◇●○■□▼▽\s]+$",
+            // ※开头的备注
+            r"※.*$",
+        ]
+        .iter()
+        .map(|pattern| Regex::new(pattern).unwrap())
+        .collect()
+    })
+}
+
+/// 规则清理的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreCleanedTitle {
+    pub title: String,
+    /// 清理后不再含有无法识别的括号内容，无需交给AI
+    pub confident: bool,
+}
+
+/// 用规则去除标题中的已知装饰，保留季号和「」/『』副标题
+pub fn pre_clean_title(raw: &str) -> PreCleanedTitle {
+    let mut title = strip_broadcast_markers(raw);
+    for regex in noise_regexes() {
+        title = regex.replace_all(&title, " ").into_owned();
+    }
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let confident = !title.is_empty() && unresolved_brackets(&title).is_empty();
+    PreCleanedTitle { title, confident }
+}
+
+/// 清理后仍然存在、且内容不是季号的括号内容
+fn unresolved_brackets(title: &str) -> Vec<String> {
+    let mut unresolved = Vec::new();
+    let mut chars = title.chars();
+
+    while let Some(c) = chars.next() {
+        if let Some(&(_, close)) = AMBIGUOUS_BRACKETS.iter().find(|(open, _)| *open == c) {
+            let content: String = chars.by_ref().take_while(|&x| x != close).collect();
+            if !contains_season(&content) {
+                unresolved.push(content);
+            }
+        } else if AMBIGUOUS_BRACKETS.iter().any(|(_, close)| *close == c) {
+            unresolved.push(c.to_string());
+        }
+    }
+
+    unresolved
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::AnimeWork;
+
+    #[test]
+    fn test_rule_based_title_cleaning() {
+        use crate::models::CleaningMethod;
+        use crate::text::cleaner::pre_clean_title;
+        use crate::text::keywords::rule_based_work;
+
+        let cleaned = pre_clean_title("【新】青のミブロ 第二期「芹沢暗殺編」【日本語吹替版】");
+        assert_eq!(cleaned.title, "青のミブロ 第二期「芹沢暗殺編」");
+        assert!(cleaned.confident);
+
+        let cleaned = pre_clean_title("ワンパンマン（第3期）［先行配信］※10/12から地上波");
+        assert_eq!(cleaned.title, "ワンパンマン（第3期）");
+        assert!(cleaned.confident);
+
+        // 无法识别的括号内容交给AI
+        let cleaned = pre_clean_title("魔法少女まどか☆マギカ（Blu-ray BOX発売記念）");
+        assert!(!cleaned.confident);
+
+        let work = rule_based_work(&AnimeWork {
+            original_title: "らんま1/2（再放送）".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(work.cleaned_title, "らんま1/2");
+        assert_eq!(work.cleaned_by, CleaningMethod::Rule);
+        assert!(work.keywords.contains(&"らんま1/2".to_string()));
+    }
+}
@@ -0,0 +1,238 @@
+//! Rule-based search keyword expansion
+//!
+//! Produces the variants the title-cleaning prompt used to ask the AI for:
+//! mid-dot and space forms, season-number spellings and subtitles with and
+//! without brackets. Used on its own when the AI is unavailable, and merged
+//! with the AI keywords so the prompt only has to ask for translations.
+
+use super::normalize::{is_separator_symbol, nfkc, normalize_keywords, search_form};
+use super::cleaner::pre_clean_title;
+use crate::models::{AnimeWork, CleaningMethod};
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// 每个作品生成的关键字数量上限
+pub const MAX_RULE_KEYWORDS: usize = 12;
+
+/// 副标题使用的括号
+const SUBTITLE_BRACKETS: &[(char, char)] = &[
+    ('「', '」'),
+    ('『', '』'),
+    ('【', '】'),
+    ('《', '》'),
+    ('〈', '〉'),
+    ('（', '）'),
+    ('(', ')'),
+];
+
+/// 配音版和重播标记
+fn marker_regexes() -> &'static [Regex] {
+    static REGEXES: OnceLock<Vec<Regex>> = OnceLock::new();
+    REGEXES.get_or_init(|| {
+        [
+            // 括号内的配音/重播说明，如【日本語吹替版】、（再放送）
+            r"[【\[［（(〈<][^【】\[\]［］（）()〈〉<>]*(?:吹替|吹き替え|再放送|リバイバル)[^【】\[\]［］（）()〈〉<>]*[】\]］）)〉>]",
+            // 单独的“再”标记，如(再)、【再】
+            r"[【\[［（(〈<]再[】\]］）)〉>]",
+            // 不带括号的标记
+            r"(?:日本語|英語|中国語)?吹き?替え?版|再放送",
+        ]
+        .iter()
+        .map(|pattern| Regex::new(pattern).unwrap())
+        .collect()
+    })
+}
+
+fn season_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"(?i)\s*(?:第\s*([0-9]+|[一二三四五六七八九十]+)\s*(?:期|季|シーズン)|season\s*([0-9]+)|\bs([0-9]+)\b|\b([0-9]+)(?:st|nd|rd|th)\s+season|(?:^|[^0-9])(?P<bare>[0-9]+)期)",
+        )
+        .unwrap()
+    })
+}
+
+/// 去除配音版、重播等标记
+pub fn strip_broadcast_markers(title: &str) -> String {
+    let mut stripped = title.to_string();
+    for regex in marker_regexes() {
+        stripped = regex.replace_all(&stripped, " ").into_owned();
+    }
+    stripped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 解析汉字数字（一到九十九）
+fn parse_chinese_numeral(text: &str) -> Option<u32> {
+    let digit = |c: char| "一二三四五六七八九".chars().position(|d| d == c).map(|p| p as u32 + 1);
+    let chars: Vec<char> = text.chars().collect();
+    match chars.as_slice() {
+        [c] if *c == '十' => Some(10),
+        [c] => digit(*c),
+        ['十', units] => Some(10 + digit(*units)?),
+        [tens, '十'] => Some(digit(*tens)? * 10),
+        [tens, '十', units] => Some(digit(*tens)? * 10 + digit(*units)?),
+        _ => None,
+    }
+}
+
+/// 汉字数字（一到九十九）
+pub fn chinese_numeral(n: u32) -> String {
+    const DIGITS: [&str; 10] = ["", "一", "二", "三", "四", "五", "六", "七", "八", "九"];
+    match (n / 10, n % 10) {
+        (0, units) => DIGITS[units as usize].to_string(),
+        (1, units) => format!("十{}", DIGITS[units as usize]),
+        (tens, units) => format!("{}十{}", DIGITS[tens as usize % 10], DIGITS[units as usize]),
+    }
+}
+
+/// 英文序数词，如 2nd、11th
+pub fn ordinal(n: u32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+/// 解析标题中的季号，返回去掉季号后的标题和季数
+pub fn parse_season(title: &str) -> Option<(String, u32)> {
+    let title = nfkc(title);
+    let captures = season_regex().captures(&title)?;
+    let number = captures.iter().skip(1).flatten().next()?.as_str();
+    let season = number.parse().ok().or_else(|| parse_chinese_numeral(number))?;
+
+    // `2期` 前面的非数字字符不属于季号
+    let mut range = captures.get(0)?.range();
+    if let Some(bare) = captures.name("bare") {
+        range.start = bare.start();
+    }
+    let base = format!("{} {}", &title[..range.start], &title[range.end..]);
+    let base = base.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!base.is_empty()).then_some((base, season))
+}
+
+/// 文本中是否含有季号
+pub fn contains_season(text: &str) -> bool {
+    season_regex().is_match(&nfkc(text))
+}
+
+/// 季号的常见写法
+pub fn season_variants(base: &str, season: u32) -> Vec<String> {
+    vec![
+        format!("{} 第{}期", base, season),
+        format!("{} 第{}季", base, season),
+        format!("{} Season {}", base, season),
+        format!("{} {} Season", base, ordinal(season)),
+    ]
+}
+
+/// 拆分主标题和括号中的副标题
+pub fn split_subtitles(title: &str) -> (String, Vec<String>) {
+    let mut main = String::new();
+    let mut subtitles = Vec::new();
+    let mut chars = title.chars();
+
+    while let Some(c) = chars.next() {
+        let Some(&(_, close)) = SUBTITLE_BRACKETS.iter().find(|(open, _)| *open == c) else {
+            main.push(c);
+            continue;
+        };
+
+        let subtitle: String = chars.by_ref().take_while(|&x| x != close).collect();
+        let subtitle = subtitle.trim();
+        if !subtitle.is_empty() {
+            subtitles.push(subtitle.to_string());
+        }
+        main.push(' ');
+    }
+
+    let main = main.split_whitespace().collect::<Vec<_>>().join(" ");
+    (main, subtitles)
+}
+
+/// 按规则生成搜索关键字
+///
+/// 依次包含：带括号的完整标题、去掉括号的完整标题、主标题、季号的各种写法，
+/// 以及上述关键字的中点/空格变体。
+pub fn expand_keywords(title: &str) -> Vec<String> {
+    let cleaned = strip_broadcast_markers(title);
+    let (main, subtitles) = split_subtitles(&cleaned);
+
+    let mut forms = vec![cleaned.clone()];
+    if !subtitles.is_empty() {
+        forms.push(format!("{} {}", main, subtitles.join(" ")));
+    }
+    forms.push(main.clone());
+    if let Some((base, season)) = parse_season(&main) {
+        forms.extend(season_variants(&base, season));
+        forms.push(base);
+    }
+
+    // 中点等分隔符号：替换为空格的版本由规范化生成，这里补充直接去掉的版本
+    let mut keywords = normalize_keywords(&forms);
+    for form in &forms {
+        if form.chars().any(|c| is_separator_symbol(c) && !c.is_whitespace()) {
+            let joined: String = form
+                .split(|c: char| is_separator_symbol(c) && !c.is_whitespace())
+                .collect();
+            let joined = search_form(&joined);
+            if !keywords.contains(&joined) {
+                keywords.push(joined);
+            }
+        }
+    }
+
+    keywords.retain(|keyword| !keyword.is_empty());
+    keywords.truncate(MAX_RULE_KEYWORDS);
+    keywords
+}
+
+/// 不经过AI，用规则清理标题并生成关键字
+pub fn rule_based_work(work: &AnimeWork) -> AnimeWork {
+    let cleaned_title = pre_clean_title(&work.original_title).title;
+    AnimeWork {
+        original_title: work.original_title.clone(),
+        keywords: expand_keywords(&cleaned_title),
+        cleaned_title,
+        air_date: work.air_date,
+        cleaned_by: CleaningMethod::Rule,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_rule_based_keywords() {
+        use crate::text::keywords::{expand_keywords, parse_season, split_subtitles, strip_broadcast_markers};
+
+        assert_eq!(strip_broadcast_markers("ぐらんぶる Season 2【日本語吹替版】"), "ぐらんぶる Season 2");
+        assert_eq!(strip_broadcast_markers("らんま1/2（再放送）"), "らんま1/2");
+        assert_eq!(strip_broadcast_markers("鬼滅の刃 柱稽古編 (再)"), "鬼滅の刃 柱稽古編");
+
+        assert_eq!(parse_season("青のミブロ 第二期"), Some(("青のミブロ".to_string(), 2)));
+        assert_eq!(parse_season("SPY×FAMILY Season 3"), Some(("SPY×FAMILY".to_string(), 3)));
+        assert_eq!(parse_season("ダンダダン 2nd Season"), Some(("ダンダダン".to_string(), 2)));
+        assert_eq!(parse_season("ミブロ2期"), Some(("ミブロ".to_string(), 2)));
+        assert_eq!(parse_season("作品 第2クール"), None);
+        assert_eq!(parse_season("薬屋のひとりごと"), None);
+
+        assert_eq!(
+            split_subtitles("青のミブロ 第二期「芹沢暗殺編」"),
+            ("青のミブロ 第二期".to_string(), vec!["芹沢暗殺編".to_string()])
+        );
+
+        let keywords = expand_keywords("青のミブロ 第二期「芹沢暗殺編」【日本語吹替版】");
+        assert_eq!(keywords[0], "青のミブロ 第二期「芹沢暗殺編」");
+        for expected in ["青のミブロ 第二期 芹沢暗殺編", "青のミブロ 第二期", "青のミブロ 第2季", "青のミブロ 2nd Season", "青のミブロ"] {
+            assert!(keywords.iter().any(|k| k == expected), "缺少关键字 {}: {:?}", expected, keywords);
+        }
+        assert!(keywords.iter().all(|k| !k.contains("吹替")));
+
+        let keywords = expand_keywords("キャッツ・アイ");
+        assert_eq!(keywords, vec!["キャッツ・アイ", "キャッツ アイ", "キャッツアイ"]);
+    }
+}
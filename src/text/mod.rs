@@ -0,0 +1,5 @@
+//! Text processing shared by keyword generation, matching and rule building
+
+pub mod cleaner;
+pub mod keywords;
+pub mod normalize;
@@ -0,0 +1,237 @@
+//! Japanese / Chinese title normalization
+//!
+//! Titles from the season tables, Bangumi and torrent names differ in width
+//! (full-width vs half-width), kana script, separator symbols (`・`, `♥`, `☆`)
+//! and Chinese script (Traditional, Simplified, Japanese shinjitai). This
+//! module folds those differences so keyword generation, candidate scoring and
+//! rule building all compare titles the same way.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use unicode_normalization::UnicodeNormalization;
+
+/// 起分隔或装饰作用的符号，搜索和规则匹配时视同空格
+const SEPARATOR_SYMBOLS: &[char] = &[
+    '・', '･', '·', '•', '♥', '♡', '❤', '☆', '★', '♪', '♫', '†', '◆', '◇', '○', '●', '△', '▽', '〜',
+    '~', '_',
+];
+
+/// 种子标题中作品名内常见的其他分隔字符，只在生成规则时视为可选的分隔符
+const RELEASE_SEPARATORS: &[char] = &['.', '/', '-'];
+
+/// 繁体字/日文新字体 → 简体字对照表，每两个字符为一组
+const SIMPLIFIED_PAIRS: &[&str] = &[
+    "編编殺杀話话語语說说説说戰战戦战鬥斗闘斗劍剑剣剑學学國国愛爱戀恋們们來来時时間间開开關关門门無无與与為为爲为會会發发電电動动畫画",
+    "機机車车東东風风飛飞馬马鳥鸟龍龙竜龙魚鱼島岛黃黄區区醫医藥药薬药樂乐楽乐聲声眾众義义儀仪異异記记讀读読读書书計计調调變变変变轉转",
+    "転转傳传伝传專专團团園园圓圆円圆図图圖图場场處处処处實实実实寶宝對对対对導导將将屆届歲岁歳岁歷历歴历氣气気气漢汉滅灭燈灯獸兽獣兽",
+    "現现產产産产當当発发盡尽監监県县縣县絕绝絶绝經经経经續续続续線线練练総总總总緑绿綠绿織织聽听聴听臉脸興兴艦舰華华萬万葉叶藝艺術术",
+    "衛卫裝装見见規规視视親亲覺觉覚觉觀观観观訓训設设訪访証证證证詩诗誠诚誰谁課课論论諸诸謎谜護护讓让譲让豐丰豊丰貓猫貝贝負负貴贵買买",
+    "賣卖売卖費费賞赏質质贈赠趙赵跡迹輕轻軽轻輪轮辦办農农這这連连進进運运過过達达遠远適适選选遺遗還还邊边辺边鄉乡郷乡醜丑釣钓鐵铁鉄铁",
+    "銀银錄录録录鏡镜鐘钟長长閃闪閉闭問问閒闲陽阳陰阴隊队際际險险険险隱隐隠隐雖虽雙双雜杂雑杂雞鸡難难雲云靈灵霊灵響响頁页頂顶項项順顺",
+    "須须預预領领頭头題题顏颜願愿類类顧顾飯饭館馆騎骑驗验験验體体髮发髪发鯨鲸鳳凤鳴鸣麗丽麵面齊齐斉齐齒齿歯齿亂乱亞亚亜亚個个備备傷伤",
+    "億亿優优兒儿児儿內内兩两両两剛刚劇剧勁劲務务勝胜勞劳労劳勢势協协卻却厲厉參参號号嗎吗嘆叹嚴严厳严圍围報报塊块壓压圧压壞坏壊坏壯壮",
+    "夢梦奪夺奮奋婦妇媽妈孫孙寫写審审寵宠尋寻層层岡冈嶺岭師师帥帅帶带帯带幫帮幹干廣广広广廳厅彈弹弾弹彌弥後后徑径從从従从復复徵征憶忆",
+    "應应応应懸悬戲戏戯戏揮挥換换撃击擊击據据擬拟擴扩攝摄敗败敵敌數数斷断於于晝昼曉晓暁晓曆历條条楊杨極极榮荣栄荣構构槍枪樣样様样樹树",
+    "橋桥檢检検检權权権权歡欢歓欢歸归帰归殘残殼壳毀毁決决沒没況况浄净淨净涙泪淚泪減减測测溫温滿满満满漁渔潛潜澤泽沢泽濟济済济濱滨浜滨",
+    "灣湾煙烟熱热燒烧焼烧營营営营爭争爺爷牆墙牽牵犧牺狀状獨独獵猎猟猎環环瑪玛畢毕疊叠療疗盜盗盤盘礙碍禍祸禮礼禪禅種种稱称穩稳穏稳窮穷",
+    "竊窃競竞筆笔節节範范築筑簡简籃篮糧粮紀纪約约紅红級级純纯紙纸紛纷細细終终組组結结給给統统絲丝綱纲網网緊紧緒绪締缔緣缘縁缘縫缝縮缩",
+    "績绩繩绳縄绳繼继継继罰罚羅罗習习聖圣聞闻職职聯联脫脱脳脑腦脑膽胆舊旧莊庄荘庄蓋盖蔵藏蘇苏蟲虫蠻蛮衝冲補补製制複复襲袭覇霸訊讯託托",
+    "許许訳译譯译試试詞词該该詳详認认誕诞誘诱誤误請请諾诺謝谢識识譜谱議议讚赞賛赞贊赞豬猪貞贞財财貨货販贩貧贫責责資资賊贼賓宾賭赌賴赖",
+    "頼赖贏赢趕赶踐践躍跃軍军軌轨軟软較较載载輝辉輩辈輸输轟轰辭辞邏逻郵邮鄰邻隣邻醬酱釋释釈释針针鈴铃鉛铅銃铳鋼钢錢钱銭钱錯错鍊炼錬炼",
+    "鍋锅鍵键鎖锁鎮镇鏈链閣阁陣阵陳陈陸陆隨随離离靜静韓韩頑顽頻频顯显顕显颯飒飢饥飲饮飼饲飽饱養养餓饿餘余駅驿驛驿駆驱驅驱騒骚騷骚驚惊",
+    "髒脏鬧闹鬱郁魯鲁鮮鲜鯊鲨鶴鹤鷹鹰鹽盐塩盐麥麦黨党點点齡龄龜龟亀龟廃废廢废惡恶悪恶惱恼悩恼態态慘惨慣惯憐怜拡扩挙举舉举摯挚擇择択择",
+    "擔担擁拥攜携斬斩晉晋暉晖暫暂曖暧棄弃樓楼歐欧殲歼氷冰渦涡湧涌湯汤滯滞潔洁澀涩渋涩濃浓瀨濑瀬濑灑洒烏乌燭烛爐炉獄狱猶犹獻献瓏珑瓊琼",
+    "甦苏畝亩癒愈瘋疯盧卢睜睁瞞瞒矯矫確确碼码磚砖祿禄禱祷稅税稲稻穀谷窩窝箏筝簽签籠笼紋纹紡纺絡络綜综綺绮維维緩缓繪绘絵绘纏缠纖纤罵骂",
+    "羨羡翹翘聰聪肅肃脅胁腎肾膠胶臟脏臓脏舖铺艶艳艷艳蒼苍蓮莲蔣蒋蕭萧薔蔷薦荐薩萨藍蓝蘭兰蘿萝虧亏蝕蚀螢萤蛍萤蠍蝎衆众裡里裏里褲裤訂订",
+    "訴诉詠咏誌志諭谕謀谋謠谣謡谣講讲謹谨譚谭讐雠貫贯貸贷賀贺賢贤賜赐贅赘贖赎躊踌軸轴輔辅輯辑轄辖迴回遊游遙遥遞递鄭郑醞酝釘钉鈍钝鉤钩",
+    "銘铭鋒锋鋭锐銳锐錦锦鍛锻鎧铠鏟铲鐳镭鑑鉴鑒鉴鑰钥閱阅閲阅闊阔闡阐隕陨霧雾靂雳韻韵頌颂頓顿頗颇頸颈顆颗顛颠颱台飄飘餅饼饗飨駒驹駕驾",
+    "駿骏騰腾驟骤髏髅鬆松魘魇鯉鲤鱗鳞鳩鸠鴉鸦鴨鸭鵬鹏鶯莺鷲鹫鸞鸾麼么黴霉齋斋斎斋龐庞仮假価价價价偽伪僞伪倉仓傘伞儂侬兇凶冊册凍冻凜凛",
+    "剝剥劃划劉刘勵励匯汇匱匮巻卷厭厌吳吴呉吴員员嗚呜噓嘘嚇吓囂嚣圏圈執执堅坚墜坠墮堕壇坛壌壤壽寿夾夹奧奥奬奖獎奖妝妆娛娱娯娱嬌娇孃娘",
+    "嬢娘寬宽寛宽寧宁寢寝尷尴屍尸屬属峽峡崗岗嶋岛巔巅幣币幾几庫库廟庙彎弯彥彦徹彻悅悦恵惠慮虑憂忧懲惩懶懒懷怀懐怀戶户戸户抜拔挾挟捜搜",
+    "掃扫掛挂揚扬損损搖摇揺摇撥拨撫抚擋挡擾扰攔拦攣挛敍叙敘叙斂敛暈晕曬晒朧胧棟栋槓杠樞枢橫横櫻樱桜樱欄栏欽钦殭僵氫氢汎泛沖冲淺浅渾浑",
+    "溝沟滾滚漲涨潰溃澆浇濁浊濕湿瀉泻灘滩煉炼燐磷燦灿爛烂犠牺獅狮甕瓮畳叠瘡疮皺皱盞盏眞真睏困矇蒙硏研碁棋磯矶礦矿祕秘稟禀穢秽窓窗竇窦",
+    "竪竖筍笋箇个簾帘籤签粵粤糾纠紐纽紳绅絆绊絹绢綿绵緋绯緻致縛缚縱纵縦纵繫系繭茧纜缆缽钵罌罂羈羁翺翱聾聋脹胀膚肤艙舱蔔卜薑姜蘊蕴蝦虾",
+    "蠅蝇袞衮覽览覧览觸触訝讶詐诈詭诡誇夸諜谍諧谐諷讽謊谎譽誉讃赞豔艳賑赈贓赃趨趋蹤踪軀躯輛辆輿舆轎轿辯辩邁迈釀酿醸酿鈔钞銅铜鋪铺錮锢",
+    "鍍镀鐮镰鑄铸鑽钻閨闺闖闯隸隶靑青韋韦頹颓顫颤飾饰餌饵饅馒駄驮駐驻驢驴鬪斗鮫鲛鯛鲷鰐鳄鱷鳄鳶鸢鴻鸿鶏鸡鷺鹭鹼碱黙默囲围",
+];
+
+fn simplified_map() -> &'static HashMap<char, char> {
+    static MAP: OnceLock<HashMap<char, char>> = OnceLock::new();
+    MAP.get_or_init(|| {
+        SIMPLIFIED_PAIRS
+            .iter()
+            .flat_map(|row| {
+                let chars: Vec<char> = row.chars().collect();
+                chars.chunks(2).map(|pair| (pair[0], pair[1])).collect::<Vec<_>>()
+            })
+            .collect()
+    })
+}
+
+/// 简体字 → 所有对应的繁体字/日文字形
+fn traditional_map() -> &'static HashMap<char, Vec<char>> {
+    static MAP: OnceLock<HashMap<char, Vec<char>>> = OnceLock::new();
+    MAP.get_or_init(|| {
+        let mut map: HashMap<char, Vec<char>> = HashMap::new();
+        for (&traditional, &simplified) in simplified_map() {
+            map.entry(simplified).or_default().push(traditional);
+        }
+        for variants in map.values_mut() {
+            variants.sort_unstable();
+        }
+        map
+    })
+}
+
+/// NFKC规范化：全角英数转半角、半角片假名转全角等
+pub fn nfkc(text: &str) -> String {
+    text.nfkc().collect()
+}
+
+/// 片假名转为平假名，其余字符不变
+pub fn fold_kana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{30A1}'..='\u{30F6}' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+/// 繁体字和日文字形转为简体字
+pub fn to_simplified(text: &str) -> String {
+    let map = simplified_map();
+    text.chars().map(|c| *map.get(&c).unwrap_or(&c)).collect()
+}
+
+/// 是否为分隔/装饰符号
+pub fn is_separator_symbol(c: char) -> bool {
+    c.is_whitespace() || SEPARATOR_SYMBOLS.contains(&c)
+}
+
+/// 把分隔符号替换为半角空格，并合并连续空白
+pub fn strip_symbols(text: &str) -> String {
+    text.split(is_separator_symbol)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 适合用作搜索关键字的形式：NFKC规范化并去除分隔符号，保留原有文字
+pub fn search_form(text: &str) -> String {
+    strip_symbols(&nfkc(text))
+}
+
+/// 用于比较的标题键：宽度、大小写、假名、简繁字形和符号差异都被消除
+pub fn match_key(text: &str) -> String {
+    fold_kana(&to_simplified(&nfkc(text).to_lowercase()))
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+/// 规范化搜索关键字：统一宽度，并为含分隔符号的关键字补充去符号的版本
+pub fn normalize_keywords(keywords: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for keyword in keywords {
+        for variant in [nfkc(keyword).trim().to_string(), search_form(keyword)] {
+            if !variant.is_empty() && !normalized.contains(&variant) {
+                normalized.push(variant);
+            }
+        }
+    }
+    normalized
+}
+
+/// 按比较键去重，保留每组中第一次出现的名称
+pub fn dedup_by_key(names: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    names
+        .into_iter()
+        .filter(|name| seen.insert(match_key(name)))
+        .collect()
+}
+
+/// 规则中可选分隔符的正则表达式，由 `SEPARATOR_SYMBOLS` 和 `RELEASE_SEPARATORS` 生成
+fn separator_class() -> &'static str {
+    static CLASS: OnceLock<String> = OnceLock::new();
+    CLASS.get_or_init(|| {
+        let symbols: String = SEPARATOR_SYMBOLS
+            .iter()
+            .chain(RELEASE_SEPARATORS)
+            .map(|c| regex::escape(&c.to_string()))
+            .collect();
+        format!(r"[\s{}]?", symbols)
+    })
+}
+
+/// 生成兼容宽度、分隔符号和简繁字形差异的正则表达式片段
+///
+/// 分隔符号变为可选的分隔符，有简繁对应的汉字变为字符组，例如
+/// `青のミブロ 芹沢暗殺編` 会匹配 `青のミブロ・芹沢暗杀编`。
+pub fn name_pattern(name: &str) -> String {
+    let normalized = nfkc(name);
+    let mut pattern = String::new();
+    let mut in_separator = false;
+
+    for c in normalized.trim().chars() {
+        if is_separator_symbol(c) {
+            if !in_separator {
+                pattern.push_str(separator_class());
+                in_separator = true;
+            }
+            continue;
+        }
+        in_separator = false;
+
+        let simplified = *simplified_map().get(&c).unwrap_or(&c);
+        let mut variants = vec![simplified];
+        if let Some(traditional) = traditional_map().get(&simplified) {
+            variants.extend(traditional);
+        }
+
+        if variants.len() > 1 {
+            pattern.push('[');
+            pattern.extend(variants);
+            pattern.push(']');
+        } else {
+            pattern.push_str(&regex::escape(&c.to_string()));
+        }
+    }
+
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_text_normalization() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::text::normalize::{match_key, name_pattern, normalize_keywords, search_form, to_simplified};
+
+        assert_eq!(search_form("ＳＰＹ×ＦＡＭＩＬＹ"), "SPY×FAMILY");
+        assert_eq!(search_form("キャッツ・アイ"), "キャッツ アイ");
+        assert_eq!(search_form("ｷｬｯﾂ♥ｱｲ"), "キャッツ アイ");
+        assert_eq!(to_simplified("青のミブロ 芹沢暗殺編"), "青のミブロ 芹泽暗杀编");
+
+        // 宽度、假名、符号和简繁差异都不影响比较
+        assert_eq!(match_key("キャッツ・アイ"), match_key("きゃっつ あい"));
+        assert_eq!(match_key("間諜家家酒"), match_key("间谍家家酒"));
+        assert_eq!(match_key("Ｒｅ：ゼロ"), match_key("re ゼロ"));
+        assert_ne!(match_key("青のミブロ"), match_key("青のミブロ 第二期"));
+
+        assert_eq!(
+            normalize_keywords(&["キャッツ・アイ".to_string(), "キャッツ アイ".to_string(), "ＣＡＴ'Ｓ ＥＹＥ".to_string()]),
+            vec!["キャッツ・アイ", "キャッツ アイ", "CAT'S EYE"]
+        );
+
+        let pattern = regex::Regex::new(&format!("^{}$", name_pattern("青のミブロ 芹沢暗殺編")))?;
+        assert!(pattern.is_match("青のミブロ 芹沢暗殺編"));
+        assert!(pattern.is_match("青のミブロ・芹泽暗杀编"));
+        assert!(pattern.is_match("青のミブロ芹沢暗殺編"));
+        assert!(pattern.is_match("青のミブロ♡芹沢暗殺編"));
+        assert!(pattern.is_match("青のミブロ〜芹沢暗殺編"));
+        assert!(!pattern.is_match("青のミブロ 新選組編"));
+
+        Ok(())
+    }
+}
@@ -1,5 +1,65 @@
 use crate::models::{BangumiResult, Statistics};
 use chrono::{Datelike, NaiveDate};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// 简单的速率限制器，保证相邻两次请求的发出时间至少间隔指定时长
+///
+/// 多个并发任务共享同一个实例时，请求会按获取顺序依次排队。
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: tokio::sync::Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_slot: tokio::sync::Mutex::new(Instant::now()),
+        }
+    }
+
+    /// 等待直到允许发出下一个请求
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+/// 用户配置目录
+///
+/// 优先使用环境变量 `BANGUMI_RULES_BUILDER_CONFIG_DIR`，否则为系统配置目录下的
+/// `bangumi-rules-builder`，都不可用时使用当前目录下的 `config`。
+pub fn config_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var("BANGUMI_RULES_BUILDER_CONFIG_DIR") {
+        return PathBuf::from(dir);
+    }
+
+    dirs::config_dir()
+        .map(|dir| dir.join("bangumi-rules-builder"))
+        .unwrap_or_else(|| PathBuf::from("config"))
+}
+
+/// 缓存目录
+///
+/// 优先使用环境变量 `BANGUMI_RULES_BUILDER_CACHE_DIR`，否则为系统缓存目录下的
+/// `bangumi-rules-builder`，都不可用时使用当前目录下的 `cache`。
+pub fn cache_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var("BANGUMI_RULES_BUILDER_CACHE_DIR") {
+        return PathBuf::from(dir);
+    }
+
+    dirs::cache_dir()
+        .map(|dir| dir.join("bangumi-rules-builder"))
+        .unwrap_or_else(|| PathBuf::from("cache"))
+}
 
 pub fn is_undetermined_date(date_str: &str) -> bool {
     // 检查是否包含具体到日一级的日期格式：YYYY/MM/DD
@@ -131,6 +191,9 @@ pub fn cache_results(results: &[BangumiResult]) -> Result<(), Box<dyn std::error
     Ok(())
 }
 
+/// 统计报告中需要提示复核的匹配置信度
+const WEAK_MATCH_CONFIDENCE: f32 = 0.85;
+
 pub fn generate_statistics_report(stats: &Statistics, bangumi_results: &[BangumiResult], failed_works: &[(String, String)]) {
     log::info!("{}", "=".repeat(60));
     log::info!("📊 程序运行统计报告");
@@ -148,6 +211,10 @@ pub fn generate_statistics_report(stats: &Statistics, bangumi_results: &[Bangumi
         "  - 经过AI处理的作品数: {}",
         stats.works_processed_by_ai
     );
+    log::info!(
+        "  - 规则清理的作品数: {}",
+        stats.works_cleaned_by_rule
+    );
 
     log::info!("Bangumi API搜索结果:");
     log::info!(
@@ -158,6 +225,10 @@ pub fn generate_statistics_report(stats: &Statistics, bangumi_results: &[Bangumi
         "  - 未找到Bangumi信息的作品: {}",
         stats.works_without_bangumi_info
     );
+    log::info!(
+        "  - 已获取条目详情的作品: {}",
+        stats.works_with_subject_details
+    );
 
     log::info!("qBittorrent规则生成:");
     log::info!("  - 生成的下载规则数量: {}", stats.qb_rules_generated);
@@ -189,6 +260,56 @@ pub fn generate_statistics_report(stats: &Statistics, bangumi_results: &[Bangumi
         "  - Token总计: {}",
         stats.ai_input_tokens + stats.ai_output_tokens
     );
+    log::info!(
+        "  - 预估Token: {} (输入 {} / 输出 {})",
+        stats.ai_estimated_input_tokens + stats.ai_estimated_output_tokens,
+        stats.ai_estimated_input_tokens,
+        stats.ai_estimated_output_tokens
+    );
+    log::info!(
+        "  - 预估花费: {:.4} {}",
+        stats.ai_estimated_cost, stats.ai_cost_currency
+    );
+    log::info!(
+        "  - 实际花费: {:.4} {}",
+        stats.ai_actual_cost, stats.ai_cost_currency
+    );
+    if let Some(budget) = stats.ai_budget {
+        log::info!("  - 预算上限: {:.4} {}", budget, stats.ai_cost_currency);
+        if stats.ai_budget_exceeded {
+            log::info!("  - ⚠️ 已超出预算，部分处理已改用非AI方式");
+        }
+    }
+
+    // 显示置信度较低的匹配，便于人工复核
+    let weak_matches: Vec<&BangumiResult> = bangumi_results
+        .iter()
+        .filter(|r| r.bangumi_id.is_some() && r.match_confidence.is_some_and(|c| c < WEAK_MATCH_CONFIDENCE))
+        .collect();
+    if !weak_matches.is_empty() {
+        log::info!("置信度较低的匹配 (低于 {}):", WEAK_MATCH_CONFIDENCE);
+        for result in weak_matches {
+            log::info!(
+                "  - {} -> {} (置信度: {:.2}, 候选数: {}, 理由: {})",
+                result.cleaned_title,
+                result.bangumi_id.unwrap_or_default(),
+                result.match_confidence.unwrap_or_default(),
+                result.candidate_count,
+                result.match_reasoning.as_deref().unwrap_or("无")
+            );
+        }
+    }
+
+    let review_results: Vec<&BangumiResult> = bangumi_results
+        .iter()
+        .filter(|r| !r.review_notes.is_empty())
+        .collect();
+    if !review_results.is_empty() {
+        log::info!("需要人工复核的作品:");
+        for result in review_results {
+            log::info!("  - {} ({})", result.cleaned_title, result.review_notes.join("; "));
+        }
+    }
 
     log::info!("未找到Bangumi信息的作品列表:");
     let mut not_found_count = 0;
@@ -208,4 +329,27 @@ pub fn generate_statistics_report(stats: &Statistics, bangumi_results: &[Bangumi
     log::info!("{}", "=".repeat(60));
     log::info!("🎉 处理完成！");
     log::info!("{}", "=".repeat(60));
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn test_rate_limiter_spacing() {
+        use crate::utils::RateLimiter;
+        use std::sync::Arc;
+
+        // 并发获取时也应按固定间隔依次放行
+        let limiter = Arc::new(RateLimiter::new(std::time::Duration::from_millis(30)));
+        let start = std::time::Instant::now();
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { limiter.acquire().await })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert!(start.elapsed() >= std::time::Duration::from_millis(90));
+    }
+}